@@ -18,6 +18,8 @@ fn main() -> Result<(), lexopt::Error> {
     };
 
     let mut parser = lexopt::Parser::from_env();
+    parser.set_plus_options(true);
+    parser.set_plus_long(true);
     while let Some(arg) = parser.next()? {
         match arg {
             Long("color") => {
@@ -38,19 +40,14 @@ fn main() -> Result<(), lexopt::Error> {
                 println!("{}", HELP);
                 std::process::exit(0);
             }
+            PlusLong(toolchain) => {
+                settings.toolchain = toolchain.to_owned();
+            }
             Value(value) => {
-                let value = value.string()?;
-                match value.as_str() {
-                    value if value.starts_with('+') => {
-                        settings.toolchain = value[1..].to_owned();
-                    }
-                    "install" => {
-                        return install(settings, parser);
-                    }
-                    value => {
-                        return Err(format!("unknown subcommand '{}'", value).into());
-                    }
-                }
+                parser.set_plus_options(false);
+                let mut subcommands = lexopt::Subcommands::new();
+                subcommands.add("install", &[], |parser| install(&settings, parser));
+                return subcommands.dispatch(value, &mut parser);
             }
             _ => return Err(arg.unexpected()),
         }
@@ -69,7 +66,7 @@ struct GlobalSettings {
     verbose: bool,
 }
 
-fn install(settings: GlobalSettings, mut parser: lexopt::Parser) -> Result<(), lexopt::Error> {
+fn install(settings: &GlobalSettings, parser: &mut lexopt::Parser) -> Result<(), lexopt::Error> {
     use lexopt::prelude::*;
 
     let mut package: Option<String> = None;
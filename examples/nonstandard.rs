@@ -1,46 +1,45 @@
 //! Some programs accept options with an unusual syntax. For example, tail
 //! accepts `-13` as an alias for `-n 13`.
 //!
-//! This program shows how to use `Parser::try_raw_args()` to handle them
-//! manually.
+//! This program shows how to use `Parser::set_negative_numbers()` to handle
+//! them: `-13` then comes through as a value that we can recognize.
 //!
 //! (Note: actual tail implementations handle it slightly differently! This
 //! is just an example.)
 
+// str::strip_prefix needs Rust 1.45, above the MSRV. That's fine for an example.
+#![allow(clippy::incompatible_msrv)]
+
+use std::ffi::OsStr;
 use std::path::PathBuf;
 
-fn parse_dashnum(parser: &mut lexopt::Parser) -> Option<u64> {
-    let mut raw = parser.try_raw_args()?;
-    let arg = raw.peek()?.to_str()?;
-    let num = arg.strip_prefix('-')?.parse::<u64>().ok()?;
-    raw.next(); // Consume the argument we just parsed
-    Some(num)
+fn parse_dashnum(arg: &OsStr) -> Option<u64> {
+    arg.to_str()?.strip_prefix('-')?.parse::<u64>().ok()
 }
 
 fn main() -> Result<(), lexopt::Error> {
     use lexopt::prelude::*;
 
     let mut parser = lexopt::Parser::from_env();
-    loop {
-        if let Some(num) = parse_dashnum(&mut parser) {
-            println!("Got number {}", num);
-        } else if let Some(arg) = parser.next()? {
-            match arg {
-                Short('f') | Long("follow") => {
-                    println!("Got --follow");
-                }
-                Short('n') | Long("number") => {
-                    let num: u64 = parser.value()?.parse()?;
+    parser.set_negative_numbers(true);
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Short('f') | Long("follow") => {
+                println!("Got --follow");
+            }
+            Short('n') | Long("number") => {
+                let num: u64 = parser.value()?.parse()?;
+                println!("Got number {}", num);
+            }
+            Value(value) => {
+                if let Some(num) = parse_dashnum(&value) {
                     println!("Got number {}", num);
-                }
-                Value(path) => {
-                    let path = PathBuf::from(path);
+                } else {
+                    let path = PathBuf::from(value);
                     println!("Got file {}", path.display());
                 }
-                _ => return Err(arg.unexpected()),
             }
-        } else {
-            break;
+            _ => return Err(arg.unexpected()),
         }
     }
 
@@ -10,15 +10,25 @@ fuzz_target!(|data: &[u8]| {
     let mut data = data;
     let mut decisions;
     if data.len() > 8 {
-        // Decide which method to call, 64 / 4 = 16 times
+        // Decide which method to call, 64 / 8 = 8 times
         decisions = u64::from_le_bytes(data[..8].try_into().unwrap());
         data = &data[8..];
     } else {
         decisions = 0;
     }
     let mut set_short_equals = true;
+    let mut set_getopt_optional_values = false;
+    let mut set_slash_options = false;
+    let mut set_single_dash_long = false;
+    let mut set_plus_options = false;
+    let mut set_plus_long = false;
     if data.len() >= 1 {
         set_short_equals = data[0] % 2 == 0;
+        set_getopt_optional_values = data[0] & 2 != 0;
+        set_slash_options = data[0] & 4 != 0;
+        set_single_dash_long = data[0] & 8 != 0;
+        set_plus_options = data[0] & 16 != 0;
+        set_plus_long = data[0] & 32 != 0;
         data = &data[1..];
     }
     let data: Vec<_> = data
@@ -30,13 +40,21 @@ fuzz_target!(|data: &[u8]| {
         .collect();
     let mut p = lexopt::Parser::from_args(data);
     p.set_short_equals(set_short_equals);
+    p.set_getopt_optional_values(set_getopt_optional_values);
+    p.set_slash_options(set_slash_options);
+    p.set_single_dash_long(set_single_dash_long);
+    p.set_plus_options(set_plus_options);
+    p.set_plus_long(set_plus_long);
     loop {
         // 0 -> Parser::next()
         // 1 -> Parser::value()
         // 2 -> Parser::values()
         // 3 -> Parser::optional_value()
-        match decisions % 4 {
-            0 => match p.next() {
+        // 4 -> Parser::attached()
+        // 5 -> Parser::reject_value()
+        // 6, 7 -> Parser::next(), so it stays the most common
+        match decisions % 8 {
+            0 | 6 | 7 => match p.next() {
                 Err(_) => assert_finished_arg(&mut p),
                 Ok(Some(_)) => (),
                 Ok(None) => break,
@@ -65,9 +83,28 @@ fuzz_target!(|data: &[u8]| {
                 assert_ne!(could_get_raw, had_optional);
                 assert_finished_arg(&mut p);
             }
+            4 => {
+                let attached = p.attached();
+                let could_get_raw = p.try_raw_args().is_some();
+                assert_eq!(attached.is_none(), could_get_raw);
+                // Peeking doesn't change anything
+                assert_eq!(p.attached(), attached);
+                assert_eq!(p.optional_value().is_some(), attached.is_some());
+                assert_finished_arg(&mut p);
+            }
+            5 => {
+                let attached = p.attached();
+                match p.reject_value() {
+                    Ok(()) => assert_ne!(attached, Some(lexopt::Attached::Equals)),
+                    Err(_) => {
+                        assert_eq!(attached, Some(lexopt::Attached::Equals));
+                        assert_finished_arg(&mut p);
+                    }
+                }
+            }
             _ => unreachable!(),
         }
-        decisions /= 4;
+        decisions /= 8;
         // This should be safe to call all the time
         let _ = p.try_raw_args();
     }
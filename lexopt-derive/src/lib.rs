@@ -0,0 +1,437 @@
+//! `#[derive(Parse)]` for [lexopt](https://docs.rs/lexopt).
+//!
+//! Don't depend on this crate directly. Enable lexopt's `derive` feature and
+//! use `#[derive(lexopt::Parse)]` instead.
+//!
+//! This crate needs Rust 1.71 or later, because of its dependencies on `syn`
+//! and `quote`. lexopt without the `derive` feature doesn't need it.
+//!
+//! The derive writes the same `while let Some(arg) = parser.next()?` loop
+//! you'd write by hand, using only lexopt's public API. If you outgrow it
+//! you can expand it (with e.g. `cargo expand`) and edit the result.
+//!
+//! # Fields
+//!
+//! Every field is an option with a long name based on the field name
+//! (`dry_run` becomes `--dry-run`), unless it's marked as positional. The
+//! type of the field decides how it's parsed:
+//!
+//! - `bool`: a flag that doesn't take a value.
+//! - `Option<T>`: an optional option.
+//! - `Option<Option<T>>`: an optional option with an optional value, like
+//!   `--color[=WHEN]`. The value has to be attached (`--color=always`),
+//!   so `--color` gives `Some(None)`.
+//! - `Vec<T>`: an option that can be repeated. Every occurrence is added.
+//! - Any other `T`: a required option, unless it has a `default`.
+//!
+//! Values are converted with `.string()?` for `String`, taken as they are
+//! for `OsString` and `PathBuf`, and parsed with `.parse()?` otherwise.
+//!
+//! # Attributes
+//!
+//! - `#[lexopt(short = 'n')]`: also accept a short option.
+//! - `#[lexopt(short)]`: use the first letter of the field name as the
+//!   short option.
+//! - `#[lexopt(long = "name")]`: use a different long option.
+//! - `#[lexopt(default = expr)]`: the value if the option isn't given.
+//! - `#[lexopt(positional)]`: take the field from positional arguments
+//!   instead. They're filled in order. A `Vec<T>` takes all remaining
+//!   arguments, and an `Option<T>` is optional.
+//!
+//! # Example
+//! ```ignore
+//! #[derive(lexopt::Parse)]
+//! struct Args {
+//!     #[lexopt(positional)]
+//!     thing: String,
+//!     #[lexopt(short, default = 1)]
+//!     number: u32,
+//!     shout: bool,
+//! }
+//! ```
+//! expands to:
+//! ```ignore
+//! impl ::lexopt::Parse for Args {
+//!     fn from_parser(parser: &mut ::lexopt::Parser) -> Result<Self, ::lexopt::Error> {
+//!         use ::lexopt::prelude::*;
+//!         let mut thing: Option<String> = None;
+//!         let mut number: u32 = 1;
+//!         let mut shout = false;
+//!         while let Some(arg) = parser.next()? {
+//!             match arg {
+//!                 Short('n') | Long("number") => {
+//!                     number = parser.value()?.parse()?;
+//!                 }
+//!                 Long("shout") => {
+//!                     shout = true;
+//!                 }
+//!                 Value(val) if thing.is_none() => {
+//!                     thing = Some(val.string()?);
+//!                 }
+//!                 _ => return Err(arg.unexpected()),
+//!             }
+//!         }
+//!         Ok(Args {
+//!             thing: thing.ok_or("missing argument THING")?,
+//!             number,
+//!             shout,
+//!         })
+//!     }
+//! }
+//! ```
+
+extern crate proc_macro;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Error, Expr, Fields, GenericArgument, Ident, LitChar, LitStr, Type};
+
+/// Derive `lexopt::Parse` for a struct with named fields.
+///
+/// See the [crate documentation](crate) for the supported fields and
+/// attributes.
+#[proc_macro_derive(Parse, attributes(lexopt))]
+pub fn derive_parse(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// The names used by the generated code. Fields with these names would
+/// shadow them.
+const RESERVED: &[&str] = &["parser", "arg", "val"];
+
+enum Kind {
+    /// `bool`
+    Flag,
+    /// `T`
+    Single,
+    /// `Option<T>`
+    Optional,
+    /// `Option<Option<T>>`, for an option with an optional value
+    OptionalValue,
+    /// `Vec<T>`
+    Repeated,
+}
+
+struct Field<'a> {
+    ident: &'a Ident,
+    /// The type of a single value, so `T` for `Option<T>` and `Vec<T>`.
+    ty: &'a Type,
+    kind: Kind,
+    positional: bool,
+    short: Option<char>,
+    long: String,
+    default: Option<Expr>,
+}
+
+impl Field<'_> {
+    /// The text to show when the field is missing.
+    fn display_name(&self) -> String {
+        if self.positional {
+            format!("argument {}", self.long.replace('-', "_").to_uppercase())
+        } else {
+            format!("option '--{}'", self.long)
+        }
+    }
+}
+
+/// Get `T` if `ty` is `wrapper<T>`.
+fn unwrap_type<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let path = match ty {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    let args = match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => args,
+        _ => return None,
+    };
+    match &args.args[0] {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }
+}
+
+fn is_type(ty: &Type, name: &str) -> bool {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == name && segment.arguments.is_none()),
+        _ => false,
+    }
+}
+
+fn parse_field(field: &syn::Field) -> Result<Field<'_>, Error> {
+    let ident = field.ident.as_ref().unwrap();
+    let name = ident.to_string();
+    let name = name.trim_start_matches("r#");
+    if RESERVED.contains(&name) {
+        return Err(Error::new(
+            ident.span(),
+            format!(
+                "a field can't be called `{}`, the generated code uses that name",
+                name
+            ),
+        ));
+    }
+
+    let (ty, kind) = if let Some(ty) = unwrap_type(&field.ty, "Option") {
+        match unwrap_type(ty, "Option") {
+            Some(ty) => (ty, Kind::OptionalValue),
+            None => (ty, Kind::Optional),
+        }
+    } else if let Some(ty) = unwrap_type(&field.ty, "Vec") {
+        (ty, Kind::Repeated)
+    } else if is_type(&field.ty, "bool") {
+        (&field.ty, Kind::Flag)
+    } else {
+        (&field.ty, Kind::Single)
+    };
+
+    let mut result = Field {
+        ident,
+        ty,
+        kind,
+        positional: false,
+        short: None,
+        long: name.replace('_', "-"),
+        default: None,
+    };
+    let mut has_long = false;
+
+    for attr in &field.attrs {
+        if !attr.path().is_ident("lexopt") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("short") {
+                if meta.input.peek(syn::Token![=]) {
+                    let short: LitChar = meta.value()?.parse()?;
+                    result.short = Some(short.value());
+                } else {
+                    result.short = name.chars().next();
+                }
+            } else if meta.path.is_ident("long") {
+                let long: LitStr = meta.value()?.parse()?;
+                if long.value().is_empty() || long.value().starts_with('-') {
+                    return Err(meta.error("expected a long option name without dashes"));
+                }
+                result.long = long.value();
+                has_long = true;
+            } else if meta.path.is_ident("default") {
+                result.default = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("positional") {
+                result.positional = true;
+            } else {
+                return Err(meta.error("unknown lexopt attribute"));
+            }
+            Ok(())
+        })?;
+    }
+
+    if result.positional {
+        if result.short.is_some() || has_long {
+            return Err(Error::new(
+                field.span(),
+                "a positional field can't have option names",
+            ));
+        }
+        if result.default.is_some() {
+            return Err(Error::new(
+                field.span(),
+                "a positional field can't have a default, use `Option<T>` instead",
+            ));
+        }
+        // A positional bool is parsed from "true" or "false" like any other value.
+        if let Kind::Flag = result.kind {
+            result.kind = Kind::Single;
+        }
+        if let Kind::OptionalValue = result.kind {
+            return Err(Error::new(
+                field.span(),
+                "a positional field can't have an optional value",
+            ));
+        }
+    } else if result.default.is_some() {
+        if let Kind::Flag | Kind::Optional | Kind::OptionalValue | Kind::Repeated = result.kind {
+            return Err(Error::new(
+                field.span(),
+                "only a field with a plain type can have a default",
+            ));
+        }
+    }
+    Ok(result)
+}
+
+/// Convert an `OsString` into a value of type `ty`.
+fn convert(value: TokenStream, ty: &Type) -> TokenStream {
+    if is_type(ty, "String") {
+        quote!(#value.string()?)
+    } else if is_type(ty, "OsString") {
+        value
+    } else if is_type(ty, "PathBuf") {
+        quote!(::std::path::PathBuf::from(#value))
+    } else {
+        quote!(#value.parse()?)
+    }
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream, Error> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    input.ident.span(),
+                    "#[derive(lexopt::Parse)] needs a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                input.ident.span(),
+                "#[derive(lexopt::Parse)] only supports structs",
+            ))
+        }
+    };
+    let fields = fields
+        .iter()
+        .map(parse_field)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut names = Vec::new();
+    let mut rest = None;
+    for field in &fields {
+        if field.positional {
+            if let Some(rest) = rest {
+                return Err(Error::new(
+                    field.ident.span(),
+                    format!("this positional field comes after `{}`, which takes all remaining arguments", rest),
+                ));
+            }
+            if let Kind::Repeated = field.kind {
+                rest = Some(field.ident);
+            }
+            continue;
+        }
+        let mut field_names = vec![format!("--{}", field.long)];
+        if let Some(short) = field.short {
+            field_names.push(format!("-{}", short));
+        }
+        for name in field_names {
+            if names.contains(&name) {
+                return Err(Error::new(
+                    field.ident.span(),
+                    format!("the option {} is used twice", name),
+                ));
+            }
+            names.push(name);
+        }
+    }
+
+    let mut locals = Vec::new();
+    let mut option_arms = Vec::new();
+    let mut positional_arms = Vec::new();
+    let mut results = Vec::new();
+
+    for field in &fields {
+        let ident = field.ident;
+        let ty = field.ty;
+
+        locals.push(match field.kind {
+            Kind::Flag => quote!(let mut #ident = false;),
+            Kind::Single => match field.default {
+                Some(ref default) => quote!(let mut #ident: #ty = #default;),
+                None => quote!(let mut #ident: Option<#ty> = None;),
+            },
+            Kind::Optional => quote!(let mut #ident: Option<#ty> = None;),
+            Kind::OptionalValue => quote!(let mut #ident: Option<Option<#ty>> = None;),
+            Kind::Repeated => quote!(let mut #ident: Vec<#ty> = Vec::new();),
+        });
+
+        let value = if field.positional {
+            convert(quote!(val), ty)
+        } else if let Kind::OptionalValue = field.kind {
+            convert(quote!(val), ty)
+        } else {
+            convert(quote!(parser.value()?), ty)
+        };
+        let body = match field.kind {
+            Kind::Flag => quote!(#ident = true;),
+            Kind::Single if field.default.is_some() => quote!(#ident = #value;),
+            Kind::Single | Kind::Optional => quote!(#ident = Some(#value);),
+            Kind::OptionalValue => quote! {
+                #ident = Some(match parser.optional_value() {
+                    Some(val) => Some(#value),
+                    None => None,
+                });
+            },
+            Kind::Repeated => quote!(#ident.push(#value);),
+        };
+
+        if field.positional {
+            positional_arms.push(match field.kind {
+                Kind::Repeated => quote! {
+                    Value(val) => {
+                        #body
+                    }
+                },
+                _ => quote! {
+                    Value(val) if #ident.is_none() => {
+                        #body
+                    }
+                },
+            });
+        } else {
+            let long = &field.long;
+            let pattern = match field.short {
+                Some(short) => quote!(Short(#short) | Long(#long)),
+                None => quote!(Long(#long)),
+            };
+            option_arms.push(quote! {
+                #pattern => {
+                    #body
+                }
+            });
+        }
+
+        results.push(match field.kind {
+            Kind::Single if field.default.is_none() => {
+                let message = format!("missing {}", field.display_name());
+                quote!(#ident: #ident.ok_or(#message)?)
+            }
+            _ => quote!(#ident),
+        });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::lexopt::Parse for #name #ty_generics #where_clause {
+            fn from_parser(
+                parser: &mut ::lexopt::Parser,
+            ) -> ::std::result::Result<Self, ::lexopt::Error> {
+                use ::lexopt::prelude::*;
+                #(#locals)*
+                while let Some(arg) = parser.next()? {
+                    match arg {
+                        #(#option_arms)*
+                        #(#positional_arms)*
+                        _ => return Err(arg.unexpected()),
+                    }
+                }
+                Ok(#name {
+                    #(#results,)*
+                })
+            }
+        }
+    })
+}
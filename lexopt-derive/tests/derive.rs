@@ -0,0 +1,103 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use lexopt::{Parse, Parser};
+
+#[derive(Debug, lexopt::Parse)]
+struct Args {
+    #[lexopt(positional)]
+    thing: String,
+    #[lexopt(positional)]
+    extra: Option<u8>,
+    #[lexopt(positional)]
+    rest: Vec<OsString>,
+    #[lexopt(short, default = 1)]
+    number: u32,
+    #[lexopt(short = 'S')]
+    shout: bool,
+    output: Option<PathBuf>,
+    #[lexopt(short = 'I', long = "include")]
+    includes: Vec<String>,
+    dry_run: bool,
+    color: Option<Option<String>>,
+}
+
+fn parse<T: Parse>(args: &[&str]) -> Result<T, lexopt::Error> {
+    T::from_parser(&mut Parser::from_args(args))
+}
+
+#[test]
+fn options() {
+    let args: Args =
+        parse(&["-n3", "--shout", "x", "-I", "a", "--include=b", "--dry-run"]).unwrap();
+    assert_eq!(args.thing, "x");
+    assert_eq!(args.number, 3);
+    assert!(args.shout);
+    assert_eq!(args.includes, &["a", "b"]);
+    assert!(args.dry_run);
+    assert_eq!(args.output, None);
+
+    let args: Args = parse(&["-Sn", "5", "--output", "out.txt", "x"]).unwrap();
+    assert!(args.shout);
+    assert_eq!(args.number, 5);
+    assert_eq!(args.output, Some(PathBuf::from("out.txt")));
+    assert!(args.includes.is_empty());
+    assert_eq!(args.color, None);
+
+    let args: Args = parse(&["--color", "x"]).unwrap();
+    assert_eq!(args.color, Some(None));
+    assert_eq!(args.thing, "x");
+    let args: Args = parse(&["--color=always", "x"]).unwrap();
+    assert_eq!(args.color, Some(Some("always".to_owned())));
+    let args: Args = parse(&["--color=", "x"]).unwrap();
+    assert_eq!(args.color, Some(Some(String::new())));
+}
+
+#[test]
+fn positionals() {
+    let args: Args = parse(&["x"]).unwrap();
+    assert_eq!(args.thing, "x");
+    assert_eq!(args.extra, None);
+    assert!(args.rest.is_empty());
+
+    let args: Args = parse(&["x", "7", "y", "--", "-z"]).unwrap();
+    assert_eq!(args.extra, Some(7));
+    assert_eq!(args.rest, &["y", "-z"]);
+}
+
+#[test]
+fn errors() {
+    let err = |args: &[&str]| parse::<Args>(args).unwrap_err().to_string();
+    assert_eq!(err(&[]), "missing argument THING");
+    assert_eq!(
+        err(&["x", "--number=a"]),
+        "cannot parse argument \"a\": invalid digit found in string"
+    );
+    assert_eq!(err(&["x", "-q"]), "invalid option '-q'");
+    assert_eq!(
+        err(&["x", "--shout=yes"]),
+        "unexpected argument for option '--shout': \"yes\""
+    );
+    assert_eq!(
+        err(&["x", "--output"]),
+        "missing argument for option '--output'"
+    );
+}
+
+#[derive(Debug, lexopt::Parse)]
+struct Required {
+    number: i64,
+}
+
+#[test]
+fn required() {
+    assert_eq!(parse::<Required>(&["--number", "-5"]).unwrap().number, -5);
+    assert_eq!(
+        parse::<Required>(&[]).unwrap_err().to_string(),
+        "missing option '--number'"
+    );
+    assert_eq!(
+        parse::<Required>(&["x"]).unwrap_err().to_string(),
+        "unexpected argument \"x\""
+    );
+}
@@ -0,0 +1,635 @@
+//! A replacement for the `getopt(1)` command from util-linux, for shell
+//! scripts:
+//!
+//! ```sh
+//! args=$(getopt -o ab:c:: -l all,block:,color:: -n myscript -- "$@") || exit 1
+//! eval set -- "$args"
+//! ```
+//!
+//! This needs the `getopt-bin` feature.
+
+use std::ffi::{OsStr, OsString};
+use std::io::Write;
+
+use lexopt::prelude::*;
+use lexopt::Error;
+
+const HELP: &str = "\
+Usage:
+ getopt <optstring> <parameters>
+ getopt [options] [--] <optstring> <parameters>
+ getopt [options] -o|--options <optstring> [options] [--] <parameters>
+
+Parse command options.
+
+Options:
+ -a, --alternative             allow long options starting with single -
+ -l, --longoptions <longopts>  the long options to be recognized
+ -n, --name <progname>         the name under which errors are reported
+ -o, --options <optstring>     the short options to be recognized
+ -q, --quiet                   disable error reporting by getopt(3)
+ -Q, --quiet-output            no normal output
+ -s, --shell <shell>           set quoting conventions to those of <shell>
+ -T, --test                    test for getopt(1) version
+ -u, --unquoted                do not quote the output
+ -h, --help                    display this help
+ -V, --version                 display version
+";
+
+/// Exit status for errors in the parsed parameters.
+const PARAMETER_ERROR: i32 = 1;
+/// Exit status for errors in getopt's own arguments.
+const USAGE_ERROR: i32 = 2;
+/// Exit status for system errors, like failing to write the output.
+const SYSTEM_ERROR: i32 = 3;
+/// Exit status for `--test`, to tell this apart from other versions.
+const TEST_EXIT: i32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HasArg {
+    No,
+    Required,
+    Optional,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shell {
+    Sh,
+    Csh,
+}
+
+/// Where to put positional arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ordering {
+    /// After all the options, like GNU's `getopt`.
+    Permute,
+    /// Stop at the first positional argument, for a `+` optstring or
+    /// `$POSIXLY_CORRECT`.
+    RequireOrder,
+    /// Leave them between the options, for a `-` optstring.
+    ReturnInOrder,
+}
+
+#[derive(Debug)]
+struct Settings {
+    alternative: bool,
+    shorts: Vec<(char, HasArg)>,
+    longs: Vec<(String, HasArg)>,
+    name: String,
+    ordering: Ordering,
+    quiet: bool,
+    quiet_output: bool,
+    quote: bool,
+    shell: Shell,
+}
+
+/// What to print, and the exit status.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Outcome {
+    stdout: Vec<u8>,
+    stderr: Vec<String>,
+    code: i32,
+}
+
+impl Outcome {
+    fn usage_error(message: String) -> Outcome {
+        Outcome {
+            stdout: Vec::new(),
+            stderr: vec![
+                format!("getopt: {}", message),
+                "Try 'getopt --help' for more information.".to_owned(),
+            ],
+            code: USAGE_ERROR,
+        }
+    }
+}
+
+fn main() {
+    let outcome = run(
+        lexopt::Parser::from_env(),
+        std::env::var_os("POSIXLY_CORRECT").is_some(),
+    );
+    for line in &outcome.stderr {
+        eprintln!("{}", line);
+    }
+    let mut stdout = std::io::stdout();
+    if let Err(err) = stdout
+        .write_all(&outcome.stdout)
+        .and_then(|()| stdout.flush())
+    {
+        // Nobody is listening if the pipe is closed, so don't complain.
+        if err.kind() != std::io::ErrorKind::BrokenPipe {
+            eprintln!("getopt: write error: {}", err);
+        }
+        std::process::exit(SYSTEM_ERROR);
+    }
+    std::process::exit(outcome.code);
+}
+
+/// Describe an error the way glibc's `getopt_long` does.
+fn describe(error: &Error) -> String {
+    match *error {
+        Error::MissingValue {
+            option: Some(ref option),
+            ..
+        } => match short_name(option) {
+            Some(option) => Error::RequiresArgument {
+                option,
+                position: None,
+            }
+            .to_string(),
+            None => format!("option '{}' requires an argument", option),
+        },
+        Error::UnexpectedValue { ref option, .. } => {
+            format!("option '{}' doesn't allow an argument", option)
+        }
+        Error::UnexpectedOption(ref option) => match short_name(option) {
+            Some(option) => Error::InvalidOption {
+                option,
+                position: None,
+            }
+            .to_string(),
+            None => format!("unrecognized option '{}'", option),
+        },
+        ref error => error.to_string(),
+    }
+}
+
+/// The name of an option like `-x`.
+fn short_name(option: &str) -> Option<char> {
+    let mut chars = option.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('-'), Some(ch), None) => Some(ch),
+        _ => None,
+    }
+}
+
+/// Parse the name and kind of an option, like `block:`.
+fn split_kind(option: &str) -> (&str, HasArg) {
+    if option.ends_with("::") {
+        (&option[..option.len() - 2], HasArg::Optional)
+    } else if option.ends_with(':') {
+        (&option[..option.len() - 1], HasArg::Required)
+    } else {
+        (option, HasArg::No)
+    }
+}
+
+fn parse_shorts(optstring: &str, settings: &mut Settings, posixly_correct: bool) {
+    let mut optstring = optstring;
+    settings.ordering = if posixly_correct {
+        Ordering::RequireOrder
+    } else {
+        Ordering::Permute
+    };
+    if optstring.starts_with('+') {
+        settings.ordering = Ordering::RequireOrder;
+        optstring = &optstring[1..];
+    } else if optstring.starts_with('-') {
+        settings.ordering = Ordering::ReturnInOrder;
+        optstring = &optstring[1..];
+    }
+    if optstring.starts_with(':') {
+        settings.quiet = true;
+        optstring = &optstring[1..];
+    }
+    let mut chars = optstring.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let mut colons = 0;
+        while chars.peek() == Some(&':') {
+            chars.next();
+            colons += 1;
+        }
+        let kind = match colons {
+            0 => HasArg::No,
+            1 => HasArg::Required,
+            _ => HasArg::Optional,
+        };
+        settings.shorts.push((ch, kind));
+    }
+}
+
+fn run(mut parser: lexopt::Parser, posixly_correct: bool) -> Outcome {
+    let mut settings = Settings {
+        alternative: false,
+        shorts: Vec::new(),
+        longs: Vec::new(),
+        name: "getopt".to_owned(),
+        ordering: Ordering::Permute,
+        quiet: false,
+        quiet_output: false,
+        quote: true,
+        shell: Shell::Sh,
+    };
+    parser.set_long_options(&[
+        "alternative",
+        "help",
+        "longoptions",
+        "name",
+        "options",
+        "quiet",
+        "quiet-output",
+        "shell",
+        "test",
+        "unquoted",
+        "version",
+    ]);
+
+    // The old form, `getopt optstring parameters`, doesn't quote.
+    let first = parser
+        .try_raw_args()
+        .and_then(|raw| raw.peek().map(OsStr::to_owned));
+    match first {
+        None => return Outcome::usage_error("missing optstring argument".to_owned()),
+        Some(ref first) if !first.to_string_lossy().starts_with('-') => settings.quote = false,
+        Some(_) => (),
+    }
+
+    let mut optstring = None;
+    let mut parameters = Vec::new();
+    loop {
+        let arg = match parser.next() {
+            Ok(Some(arg)) => arg,
+            Ok(None) => break,
+            Err(err) => return Outcome::usage_error(describe(&err)),
+        };
+        let result: Result<(), Error> = match arg {
+            Short('a') | Long("alternative") => {
+                settings.alternative = true;
+                Ok(())
+            }
+            Short('h') | Long("help") => {
+                return Outcome {
+                    stdout: HELP.as_bytes().to_vec(),
+                    ..Outcome::default()
+                }
+            }
+            Short('l') | Long("longoptions") => parser.value().and_then(|value| {
+                for option in value
+                    .string()?
+                    .split(|ch: char| ch == ',' || ch.is_whitespace())
+                {
+                    if !option.is_empty() {
+                        let (name, kind) = split_kind(option);
+                        settings.longs.push((name.to_owned(), kind));
+                    }
+                }
+                Ok(())
+            }),
+            Short('n') | Long("name") => parser.value().and_then(|value| {
+                settings.name = value.string()?;
+                Ok(())
+            }),
+            Short('o') | Long("options") => parser.value().and_then(|value| {
+                optstring = Some(value.string()?);
+                Ok(())
+            }),
+            Short('q') | Long("quiet") => {
+                settings.quiet = true;
+                Ok(())
+            }
+            Short('Q') | Long("quiet-output") => {
+                settings.quiet_output = true;
+                Ok(())
+            }
+            Short('s') | Long("shell") => parser.value().and_then(|value| {
+                settings.shell = match value.to_str() {
+                    Some("sh") | Some("bash") => Shell::Sh,
+                    Some("csh") | Some("tcsh") => Shell::Csh,
+                    _ => {
+                        return Err("unknown shell after -s or --shell argument".into());
+                    }
+                };
+                Ok(())
+            }),
+            Short('T') | Long("test") => {
+                return Outcome {
+                    code: TEST_EXIT,
+                    ..Outcome::default()
+                }
+            }
+            Short('u') | Long("unquoted") => {
+                settings.quote = false;
+                Ok(())
+            }
+            Short('V') | Long("version") => {
+                return Outcome {
+                    stdout: format!("getopt from lexopt {}\n", env!("CARGO_PKG_VERSION"))
+                        .into_bytes(),
+                    ..Outcome::default()
+                }
+            }
+            Value(value) => {
+                if optstring.is_none() {
+                    match value.into_string() {
+                        Ok(value) => optstring = Some(value),
+                        Err(value) => {
+                            return Outcome::usage_error(describe(&Error::NonUnicodeValue(value)))
+                        }
+                    }
+                } else {
+                    parameters.push(value);
+                }
+                // Like util-linux, stop at the first positional argument.
+                parameters.extend(parser.raw_args().unwrap());
+                break;
+            }
+            _ => Err(arg.unexpected()),
+        };
+        if let Err(err) = result {
+            return Outcome::usage_error(describe(&err));
+        }
+    }
+
+    let optstring = match optstring {
+        Some(optstring) => optstring,
+        None => return Outcome::usage_error("missing optstring argument".to_owned()),
+    };
+    parse_shorts(&optstring, &mut settings, posixly_correct);
+    normalize(&settings, parameters)
+}
+
+/// Parse the parameters and print them in a normal form.
+fn normalize(settings: &Settings, parameters: Vec<OsString>) -> Outcome {
+    let mut parser = lexopt::Parser::from_args(parameters);
+    parser.set_short_equals(false);
+    let long_names: Vec<&str> = settings
+        .longs
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect();
+    parser.set_long_options(&long_names);
+    if settings.alternative {
+        parser.set_single_dash_long_options(&long_names);
+    }
+
+    let mut words: Vec<Vec<u8>> = Vec::new();
+    let mut positionals = Vec::new();
+    let mut errors = Vec::new();
+    loop {
+        // Everything after "--" is positional, even with ReturnInOrder.
+        if let Some(mut raw) = parser.try_raw_args() {
+            if raw.peek() == Some(OsStr::new("--")) {
+                raw.next();
+                positionals.extend(raw);
+                break;
+            }
+        }
+        let arg = match parser.next() {
+            Ok(Some(arg)) => arg,
+            Ok(None) => break,
+            Err(err) => {
+                errors.push(describe(&err));
+                continue;
+            }
+        };
+        let (option, kind) = match arg {
+            Short(ch) => match settings.shorts.iter().find(|&&(known, _)| known == ch) {
+                Some(&(_, kind)) => (format!("-{}", ch), kind),
+                // lexopt leaves a lone "-n" to us, because it doesn't know
+                // whether that's a short option.
+                None if settings.alternative && is_lone_short(&parser) => {
+                    match settings.abbreviation(ch) {
+                        Ok(Some(found)) => found,
+                        Ok(None) => {
+                            errors.push(describe(&Short(ch).unexpected()));
+                            continue;
+                        }
+                        Err(err) => {
+                            errors.push(describe(&err));
+                            continue;
+                        }
+                    }
+                }
+                None => {
+                    errors.push(describe(&Short(ch).unexpected()));
+                    continue;
+                }
+            },
+            Long(name) => match settings.longs.iter().find(|(known, _)| known == name) {
+                Some(&(_, kind)) => (format!("--{}", name), kind),
+                None => {
+                    errors.push(describe(&arg.unexpected()));
+                    continue;
+                }
+            },
+            Value(value) => {
+                match settings.ordering {
+                    Ordering::Permute => positionals.push(value),
+                    Ordering::RequireOrder => {
+                        positionals.push(value);
+                        positionals.extend(parser.raw_args().unwrap());
+                        break;
+                    }
+                    Ordering::ReturnInOrder => words.push(settings.quote(&value)),
+                }
+                continue;
+            }
+            _ => unreachable!(),
+        };
+        match kind {
+            HasArg::No => match parser.reject_value() {
+                Ok(()) => words.push(option.into_bytes()),
+                Err(err) => errors.push(describe(&err)),
+            },
+            HasArg::Required => match parser.value() {
+                Ok(value) => {
+                    words.push(option.into_bytes());
+                    words.push(settings.quote(&value));
+                }
+                Err(err) => errors.push(describe(&err)),
+            },
+            HasArg::Optional => {
+                let value = parser.optional_value().unwrap_or_default();
+                words.push(option.into_bytes());
+                words.push(settings.quote(&value));
+            }
+        }
+    }
+
+    let mut outcome = Outcome::default();
+    if !errors.is_empty() {
+        outcome.code = PARAMETER_ERROR;
+    }
+    if !settings.quiet {
+        outcome.stderr = errors
+            .into_iter()
+            .map(|error| format!("{}: {}", settings.name, error))
+            .collect();
+    }
+    if !settings.quiet_output {
+        words.push(b"--".to_vec());
+        words.extend(positionals.iter().map(|value| settings.quote(value)));
+        for word in words {
+            outcome.stdout.push(b' ');
+            outcome.stdout.extend(word);
+        }
+        outcome.stdout.push(b'\n');
+    }
+    outcome
+}
+
+/// Whether the short option that was just returned is a whole argument,
+/// like `-n` but not `-an` or `-nvalue`.
+fn is_lone_short(parser: &lexopt::Parser) -> bool {
+    parser.attached().is_none()
+        && parser
+            .current_position()
+            .map_or(false, |position| position.offset == 1)
+}
+
+impl Settings {
+    /// Expand `-n` to a long option that starts with `n`, the way
+    /// `getopt_long_only` does for arguments that aren't short options.
+    fn abbreviation(&self, ch: char) -> Result<Option<(String, HasArg)>, Error> {
+        let candidates: Vec<&(String, HasArg)> = self
+            .longs
+            .iter()
+            .filter(|(name, _)| name.starts_with(ch))
+            .collect();
+        match candidates.len() {
+            0 => Ok(None),
+            1 => Ok(Some((format!("--{}", candidates[0].0), candidates[0].1))),
+            _ => Err(Error::AmbiguousOption {
+                option: format!("-{}", ch),
+                candidates: candidates
+                    .into_iter()
+                    .map(|(name, _)| format!("-{}", name))
+                    .collect(),
+                position: None,
+            }),
+        }
+    }
+
+    /// Quote a value for the shell, unless quoting is off.
+    fn quote(&self, value: &OsStr) -> Vec<u8> {
+        #[cfg(unix)]
+        let bytes = std::os::unix::ffi::OsStrExt::as_bytes(value).to_vec();
+        #[cfg(not(unix))]
+        let bytes = value.to_string_lossy().into_owned().into_bytes();
+
+        if !self.quote {
+            return bytes;
+        }
+        let mut quoted = vec![b'\''];
+        for byte in bytes {
+            match byte {
+                b'\'' => quoted.extend_from_slice(b"'\\''"),
+                b'!' if self.shell == Shell::Csh => quoted.extend_from_slice(b"'\\!'"),
+                b'\n' if self.shell == Shell::Csh => quoted.extend_from_slice(b"\\n"),
+                _ => quoted.push(byte),
+            }
+        }
+        quoted.push(b'\'');
+        quoted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn getopt(args: &[&str]) -> Outcome {
+        run(lexopt::Parser::from_args(args), false)
+    }
+
+    fn stdout(outcome: &Outcome) -> &str {
+        std::str::from_utf8(&outcome.stdout).unwrap()
+    }
+
+    #[test]
+    fn normalizing() {
+        let outcome = getopt(&[
+            "-o",
+            "ab:c::",
+            "-l",
+            "all,block:,color::",
+            "--",
+            "x",
+            "-ab1",
+            "--bl=2",
+            "-c",
+            "--color=always",
+            "it's",
+            "--",
+            "-a",
+        ]);
+        assert_eq!(
+            stdout(&outcome),
+            " -a -b '1' --block '2' -c '' --color 'always' -- 'x' 'it'\\''s' '-a'\n"
+        );
+        assert!(outcome.stderr.is_empty());
+        assert_eq!(outcome.code, 0);
+
+        let outcome = getopt(&["-u", "-o", "+a", "--", "-a", "x", "-a"]);
+        assert_eq!(stdout(&outcome), " -a -- x -a\n");
+        let outcome = getopt(&["-o", "-a", "--", "-a", "x", "-a"]);
+        assert_eq!(stdout(&outcome), " -a 'x' -a --\n");
+        let outcome = getopt(&["ab:", "-b", "x y"]);
+        assert_eq!(stdout(&outcome), " -b x y --\n");
+        let outcome = getopt(&[
+            "-a", "-s", "tcsh", "-o", "", "-l", "name:", "--", "-name", "hi!",
+        ]);
+        assert_eq!(stdout(&outcome), " --name 'hi'\\!'' --\n");
+
+        let outcome = getopt(&["-a", "-o", "a", "-l", "name:", "--", "-n", "x", "-nam=y"]);
+        assert_eq!(stdout(&outcome), " --name 'x' --name 'y' --\n");
+        let outcome = getopt(&["-a", "-o", "n", "-l", "name:", "--", "-n", "-an"]);
+        assert_eq!(stdout(&outcome), " -n -n --\n");
+        assert_eq!(outcome.stderr, &["getopt: invalid option -- 'a'"]);
+        let outcome = getopt(&["-a", "-o", "", "-l", "name,new", "--", "-n", "-ne"]);
+        assert_eq!(stdout(&outcome), " --new --\n");
+        assert_eq!(
+            outcome.stderr,
+            &["getopt: option '-n' is ambiguous; possibilities: '-name' '-new'"]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let outcome = getopt(&[
+            "-n", "app", "-o", "ab:", "-l", "all", "--", "-xa", "--al=1", "--bad", "-b",
+        ]);
+        assert_eq!(stdout(&outcome), " -a --\n");
+        assert_eq!(
+            outcome.stderr,
+            &[
+                "app: invalid option -- 'x'",
+                "app: option '--all' doesn't allow an argument",
+                "app: unrecognized option '--bad'",
+                "app: option requires an argument -- 'b'",
+            ]
+        );
+        assert_eq!(outcome.code, PARAMETER_ERROR);
+
+        let outcome = getopt(&["-q", "-Q", "-o", "a", "--", "-x"]);
+        assert_eq!(
+            outcome,
+            Outcome {
+                code: PARAMETER_ERROR,
+                ..Outcome::default()
+            }
+        );
+        assert_eq!(getopt(&["-T"]).code, TEST_EXIT);
+
+        let outcome = getopt(&["-s", "fish", "a"]);
+        assert_eq!(
+            outcome.stderr[0],
+            "getopt: unknown shell after -s or --shell argument"
+        );
+        assert_eq!(outcome.code, USAGE_ERROR);
+        assert_eq!(
+            getopt(&["-o"]).stderr[0],
+            "getopt: option requires an argument -- 'o'"
+        );
+        assert_eq!(
+            getopt(&["--name"]).stderr[0],
+            "getopt: option '--name' requires an argument"
+        );
+        assert_eq!(getopt(&[]).stderr[0], "getopt: missing optstring argument");
+        assert_eq!(
+            getopt(&["-u"]).stderr[0],
+            "getopt: missing optstring argument"
+        );
+    }
+}
@@ -0,0 +1,295 @@
+//! Show how lexopt splits up a command line, for debugging and bug reports:
+//!
+//! ```text
+//! $ lexopt-explain -- -xo=out file
+//! CALL RESULT        ARG BYTES TEXT
+//! next Short('x')    0   1..2  x
+//! next Short('o')    0   2..3  o
+//! next error: unexpected argument for option '-o': "out"
+//!                    0   4..7  out
+//! next Value("file") 1   0..4  file
+//! next None
+//! ```
+//!
+//! Use `--calls` to call other methods than `Parser::next`, and the other
+//! options to change the parser's settings.
+//!
+//! This needs the `explain-bin` feature.
+
+use std::ffi::OsString;
+
+use lexopt::prelude::*;
+use lexopt::{Parser, Position};
+
+const HELP: &str = "\
+Usage: lexopt-explain [OPTIONS] [--] ARG...
+
+Show how lexopt parses a command line.
+
+Options:
+      --calls=CALLS           Methods to call first, separated by commas:
+                              next, value, values, optional-value,
+                              reject-value, raw-args. Afterwards next is
+                              called until the arguments run out.
+      --no-short-equals       Call set_short_equals(false)
+      --negative-numbers      Call set_negative_numbers(true)
+      --getopt-optional-values
+                              Call set_getopt_optional_values(true)
+      --slash-options         Call set_slash_options(true)
+      --single-dash-long      Call set_single_dash_long(true)
+      --plus-options          Call set_plus_options(true)
+  -h, --help                  Print help
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Call {
+    Next,
+    Value,
+    Values,
+    OptionalValue,
+    RejectValue,
+    RawArgs,
+}
+
+impl Call {
+    fn name(self) -> &'static str {
+        match self {
+            Call::Next => "next",
+            Call::Value => "value",
+            Call::Values => "values",
+            Call::OptionalValue => "optional-value",
+            Call::RejectValue => "reject-value",
+            Call::RawArgs => "raw-args",
+        }
+    }
+}
+
+impl std::str::FromStr for Call {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Call, String> {
+        [
+            Call::Next,
+            Call::Value,
+            Call::Values,
+            Call::OptionalValue,
+            Call::RejectValue,
+            Call::RawArgs,
+        ]
+        .iter()
+        .cloned()
+        .find(|call| call.name() == name)
+        .ok_or_else(|| format!("unknown call '{}'", name))
+    }
+}
+
+/// One row of the table.
+struct Row {
+    call: Call,
+    result: String,
+    position: Option<Position>,
+}
+
+fn main() -> Result<(), lexopt::Error> {
+    let mut calls = Vec::new();
+    let mut settings: Vec<fn(&mut Parser)> = Vec::new();
+    let mut args: Vec<OsString> = Vec::new();
+
+    let mut parser = Parser::from_env();
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Long("calls") => {
+                for call in parser.value()?.string()?.split(',') {
+                    calls.push(call.trim().parse()?);
+                }
+            }
+            Long("no-short-equals") => settings.push(|p| p.set_short_equals(false)),
+            Long("negative-numbers") => settings.push(|p| p.set_negative_numbers(true)),
+            Long("getopt-optional-values") => settings.push(|p| p.set_getopt_optional_values(true)),
+            Long("slash-options") => settings.push(|p| p.set_slash_options(true)),
+            Long("single-dash-long") => settings.push(|p| p.set_single_dash_long(true)),
+            Long("plus-options") => settings.push(|p| p.set_plus_options(true)),
+            Short('h') | Long("help") => {
+                print!("{}", HELP);
+                return Ok(());
+            }
+            Value(value) => {
+                args.push(value);
+                args.extend(parser.raw_args()?);
+            }
+            _ => return Err(arg.unexpected()),
+        }
+    }
+
+    print!("{}", explain(&settings, &args, &calls));
+    Ok(())
+}
+
+/// Run the calls on a parser configured by `settings` and render the table.
+fn explain(settings: &[fn(&mut Parser)], args: &[OsString], calls: &[Call]) -> String {
+    let mut parser = Parser::from_args(args);
+    for setting in settings {
+        setting(&mut parser);
+    }
+    let mut rows = Vec::new();
+    let mut calls = calls.iter().cloned();
+    loop {
+        let call = calls.next().unwrap_or(Call::Next);
+        let row = run(&mut parser, call);
+        let finished = call == Call::Next && row.result == "None";
+        rows.push(row);
+        if finished {
+            break;
+        }
+    }
+    render(&rows, args)
+}
+
+fn run(parser: &mut Parser, call: Call) -> Row {
+    let result = match call {
+        Call::Next => parser.next().map(|arg| match arg {
+            Some(arg) => format!("{:?}", arg),
+            None => "None".to_owned(),
+        }),
+        Call::Value => parser.value().map(|value| format!("{:?}", value)),
+        Call::Values => parser
+            .values()
+            .map(|values| format!("{:?}", values.collect::<Vec<_>>())),
+        Call::OptionalValue => Ok(format!("{:?}", parser.optional_value())),
+        Call::RejectValue => parser.reject_value().map(|()| "Ok".to_owned()),
+        Call::RawArgs => parser
+            .raw_args()
+            .map(|raw| format!("{:?}", raw.collect::<Vec<_>>())),
+    };
+    match result {
+        Ok(result) => Row {
+            call,
+            position: match call {
+                Call::RawArgs | Call::RejectValue => None,
+                Call::Next if result == "None" => None,
+                Call::OptionalValue if result == "None" => None,
+                _ => parser.current_position(),
+            },
+            result,
+        },
+        Err(err) => Row {
+            call,
+            result: format!("error: {}", err),
+            position: parser.error_position(&err),
+        },
+    }
+}
+
+fn render(rows: &[Row], args: &[OsString]) -> String {
+    let cells: Vec<[String; 5]> = rows
+        .iter()
+        .map(|row| {
+            let mut cells = [
+                row.call.name().to_owned(),
+                row.result.clone(),
+                String::new(),
+                String::new(),
+                String::new(),
+            ];
+            if let Some(position) = row.position {
+                let end = position.offset + position.len;
+                cells[2] = position.index.to_string();
+                cells[3] = format!("{}..{}", position.offset, end);
+                cells[4] = args
+                    .get(position.index)
+                    .map(|arg| arg.to_string_lossy())
+                    .and_then(|arg| arg.get(position.offset..end).map(str::to_owned))
+                    .unwrap_or_default();
+            }
+            cells
+        })
+        .collect();
+
+    let header = ["CALL", "RESULT", "ARG", "BYTES", "TEXT"];
+    let mut widths: Vec<usize> = header.iter().map(|title| title.len()).collect();
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    // Errors tend to be long, so they get a line of their own instead of
+    // making the column wider.
+    let result_width = cells
+        .iter()
+        .filter(|row| !row[1].starts_with("error: "))
+        .map(|row| row[1].chars().count())
+        .chain(Some(header[1].len()))
+        .max()
+        .unwrap();
+    widths[1] = result_width;
+
+    let mut out = String::new();
+    let mut line = |cells: &[String]| {
+        let mut text = String::new();
+        for (i, cell) in cells.iter().enumerate() {
+            if i > 0 {
+                text.push(' ');
+            }
+            if i == 1 && cell.chars().count() > widths[1] {
+                text.push_str(cell);
+                text.push('\n');
+                text.push_str(&" ".repeat(widths[0] + widths[1] + 1));
+            } else {
+                text.push_str(&format!("{:<width$}", cell, width = widths[i]));
+            }
+        }
+        out.push_str(text.trim_end());
+        out.push('\n');
+    };
+    line(
+        &header
+            .iter()
+            .map(|title| (*title).to_owned())
+            .collect::<Vec<_>>(),
+    );
+    for row in &cells {
+        line(row);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn explain_args(settings: &[fn(&mut Parser)], args: &[&str], calls: &[Call]) -> String {
+        let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+        explain(settings, &args, calls)
+    }
+
+    #[test]
+    fn table() {
+        assert_eq!(
+            explain_args(&[], &["-xo=out", "file"], &[]),
+            "\
+CALL RESULT        ARG BYTES TEXT
+next Short('x')    0   1..2  x
+next Short('o')    0   2..3  o
+next error: unexpected argument for option '-o': \"out\"
+                   0   4..7  out
+next Value(\"file\") 1   0..4  file
+next None
+"
+        );
+
+        assert_eq!(
+            explain_args(
+                &[|p| p.set_short_equals(false)],
+                &["-o=x", "--", "y"],
+                &[Call::Next, Call::OptionalValue, Call::RawArgs]
+            ),
+            "\
+CALL           RESULT      ARG BYTES TEXT
+next           Short('o')  0   1..2  o
+optional-value Some(\"=x\")  0   2..4  =x
+raw-args       [\"--\", \"y\"]
+next           None
+"
+        );
+    }
+}
@@ -0,0 +1,784 @@
+//! Deserializing command line options into a struct with serde.
+
+use std::cell::Cell;
+use std::ffi::OsString;
+use std::fmt::Display;
+
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+};
+
+use crate::{Arg, Error, Parser, ValueExt};
+
+/// Parse the command line arguments of the current process into a struct
+/// that implements [`serde::Deserialize`].
+///
+/// See [`from_parser`] for how fields are matched to options.
+///
+/// This requires the `serde` feature.
+///
+/// # Example
+/// ```ignore
+/// #[derive(serde::Deserialize)]
+/// struct Config {
+///     verbose: bool,
+///     jobs: Option<u32>,
+///     include: Vec<String>,
+///     output_dir: std::path::PathBuf,
+/// }
+///
+/// fn main() -> Result<(), lexopt::Error> {
+///     // app --verbose --include a --include b --output-dir out
+///     let config: Config = lexopt::from_env()?;
+///     Ok(())
+/// }
+/// ```
+pub fn from_env<T: DeserializeOwned>() -> Result<T, Error> {
+    from_parser(&mut Parser::from_env())
+}
+
+/// Parse the remaining arguments of `parser` into a struct that implements
+/// [`serde::Deserialize`].
+///
+/// Each field is a long option. Underscores in field names are written as
+/// dashes, so `dry_run` is `--dry-run`, though `--dry_run` works too. A
+/// field with a one-letter name is a short option.
+///
+/// - `bool` fields are flags. They're `false` unless the option is given,
+///   or if it's negated with [`Parser::set_negatable_options`].
+/// - `Vec` fields can be given multiple times, or not at all, and take
+///   every value up to the next option, as by [`Parser::values`]. So
+///   `--include a b` and `--include a --include b` are the same.
+/// - `Option` fields may be left out.
+/// - Other fields take a value and are required. If they're given more
+///   than once the last value wins. Values are parsed with [`FromStr`]
+///   for numbers, `bool` and `char`, and enums can be selected by variant
+///   name.
+///
+/// Unknown options and positional arguments are reported as
+/// [`Error::UnexpectedOption`] (or [`Error::MisspelledOption`]) and
+/// [`Error::UnexpectedArgument`], values
+/// that can't be parsed as [`Error::ParsingFailed`], and missing fields as
+/// [`Error::Custom`].
+///
+/// Attributes like `#[serde(rename)]` and `#[serde(default)]` are respected.
+///
+/// This requires the `serde` feature.
+///
+/// [`FromStr`]: std::str::FromStr
+pub fn from_parser<T: DeserializeOwned>(parser: &mut Parser) -> Result<T, Error> {
+    let fields = probe_fields::<T>()?;
+    let shapes: Vec<Shape> = fields
+        .iter()
+        .map(|&field| probe_shape::<T>(field))
+        .collect();
+
+    let mut found: Vec<Option<Found>> = vec![None; fields.len()];
+    while let Some(arg) = parser.next()? {
+        let index = match arg {
+            Arg::Short(short) => fields.iter().position(|&field| {
+                let mut chars = field.chars();
+                chars.next() == Some(short) && chars.next().is_none()
+            }),
+            Arg::Long(name) => fields.iter().position(|&field| {
+                field.chars().count() > 1 && field.replace('_', "-") == name.replace('_', "-")
+            }),
+            _ => None,
+        };
+        let index = match index {
+            Some(index) => index,
+            None => {
+                let names: Vec<String> = fields
+                    .iter()
+                    .filter(|field| field.chars().count() > 1)
+                    .map(|field| field.replace('_', "-"))
+                    .collect();
+                let names: Vec<&str> = names.iter().map(String::as_str).collect();
+                let error = arg.unexpected_among(&names);
+                return Err(error.with_position(parser.current_position()));
+            }
+        };
+        let values = match shapes[index].kind {
+            Kind::Flag => {
+                parser.reject_value()?;
+                found[index] = Some(Found::Flag(!parser.negated()));
+                continue;
+            }
+            Kind::Seq => parser.values()?.collect(),
+            Kind::Value => vec![parser.value()?],
+        };
+        match found[index] {
+            Some(Found::Values(ref mut existing)) => existing.extend(values),
+            _ => found[index] = Some(Found::Values(values)),
+        }
+    }
+
+    let mut entries = Vec::new();
+    for ((&field, shape), found) in fields.iter().zip(shapes).zip(found) {
+        let found = match (found, shape.kind) {
+            (Some(found), _) => found,
+            // Leave it to serde to use None or the default.
+            (None, _) if shape.optional => continue,
+            (None, Kind::Flag) => Found::Flag(false),
+            (None, Kind::Seq) => Found::Values(Vec::new()),
+            (None, Kind::Value) => continue,
+        };
+        entries.push((field, found, shape.kind));
+    }
+    T::deserialize(Struct(entries))
+}
+
+/// The name of the option for a field, like `--dry-run`.
+fn option_name(field: &str) -> String {
+    if field.chars().count() == 1 {
+        format!("-{}", field)
+    } else {
+        format!("--{}", field.replace('_', "-"))
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Error {
+        Error::Custom(msg.to_string().into())
+    }
+
+    fn missing_field(field: &'static str) -> Error {
+        format!("missing option '{}'", option_name(field)).into()
+    }
+}
+
+// Before parsing we need to know which options take a value, and that
+// depends on the type of each field. We find out by starting to
+// deserialize a struct with a single field, then stopping with an error
+// as soon as the field's type has asked for what it wants.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// A `bool`, which doesn't take a value.
+    Flag,
+    /// A sequence, which takes any number of values.
+    Seq,
+    /// Anything else, which takes a single value.
+    Value,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Shape {
+    kind: Kind,
+    /// Whether the field is an `Option`.
+    optional: bool,
+}
+
+fn stop() -> Error {
+    Error::Custom("stopped probing".into())
+}
+
+fn probe_fields<T: DeserializeOwned>() -> Result<&'static [&'static str], Error> {
+    let fields = Cell::new(None);
+    let _ = T::deserialize(FieldsProbe(&fields));
+    fields
+        .get()
+        .ok_or_else(|| "only structs can be deserialized from the command line".into())
+}
+
+fn probe_shape<T: DeserializeOwned>(field: &'static str) -> Shape {
+    let shape = Cell::new(Shape {
+        kind: Kind::Value,
+        optional: false,
+    });
+    let _ = T::deserialize(ShapeProbe {
+        field: Some(field),
+        shape: &shape,
+    });
+    shape.get()
+}
+
+/// Records the field names of a struct.
+struct FieldsProbe<'a>(&'a Cell<Option<&'static [&'static str]>>);
+
+impl<'de, 'a> de::Deserializer<'de> for FieldsProbe<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(stop())
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.0.set(Some(fields));
+        Err(stop())
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+/// Presents a struct with a single field, whose shape ends up in `shape`.
+struct ShapeProbe<'a> {
+    field: Option<&'static str>,
+    shape: &'a Cell<Shape>,
+}
+
+impl<'de, 'a> de::Deserializer<'de> for ShapeProbe<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de, 'a> MapAccess<'de> for ShapeProbe<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.field.take() {
+            Some(field) => seed.deserialize(field.into_deserializer()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value, Error> {
+        seed.deserialize(ValueProbe(self.shape))
+    }
+}
+
+/// Records what a field's type asks for.
+struct ValueProbe<'a>(&'a Cell<Shape>);
+
+impl<'a> ValueProbe<'a> {
+    fn stop_at(self, kind: Kind) -> Error {
+        let mut shape = self.0.get();
+        shape.kind = kind;
+        self.0.set(shape);
+        stop()
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for ValueProbe<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(self.stop_at(Kind::Value))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(self.stop_at(Kind::Flag))
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(self.stop_at(Kind::Seq))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let mut shape = self.0.get();
+        shape.optional = true;
+        self.0.set(shape);
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct tuple tuple_struct map struct enum
+        identifier ignored_any
+    }
+}
+
+/// What was found on the command line for a field.
+#[derive(Debug, Clone)]
+enum Found {
+    Flag(bool),
+    Values(Vec<OsString>),
+}
+
+/// The fields that were found, presented as a struct.
+struct Struct(Vec<(&'static str, Found, Kind)>);
+
+impl<'de> de::Deserializer<'de> for Struct {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(Entries {
+            entries: self.0.into_iter(),
+            value: None,
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct Entries {
+    entries: std::vec::IntoIter<(&'static str, Found, Kind)>,
+    value: Option<(Found, Kind)>,
+}
+
+impl<'de> MapAccess<'de> for Entries {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.entries.next() {
+            Some((field, found, kind)) => {
+                self.value = Some((found, kind));
+                seed.deserialize(field.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value, Error> {
+        let (found, kind) = self.value.take().expect("value without a key");
+        match found {
+            // Types that parse a string after deserializing it report errors
+            // only now.
+            Found::Values(ref values) if kind == Kind::Value => {
+                let value = values.last().unwrap().to_string_lossy().into_owned();
+                in_value(value, seed.deserialize(found.clone()))
+            }
+            found => seed.deserialize(found),
+        }
+    }
+}
+
+/// Forward to the last value, for fields that take a single value.
+macro_rules! forward_to_last_value {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self {
+                    Found::Flag(flag) => visitor.visit_bool(flag),
+                    // Only sequences can be empty.
+                    Found::Values(mut values) => Value(values.pop().unwrap()).$method(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Found {
+    type Error = Error;
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Found::Flag(flag) => visitor.visit_bool(flag),
+            Found::Values(values) => visitor.visit_seq(Values(values.into_iter())),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self {
+            Found::Flag(flag) => visitor.visit_bool(flag),
+            Found::Values(mut values) => {
+                Value(values.pop().unwrap()).deserialize_enum(name, variants, visitor)
+            }
+        }
+    }
+
+    forward_to_last_value! {
+        deserialize_any deserialize_bool deserialize_i8 deserialize_i16
+        deserialize_i32 deserialize_i64 deserialize_u8 deserialize_u16
+        deserialize_u32 deserialize_u64 deserialize_f32 deserialize_f64
+        deserialize_char deserialize_str deserialize_string
+        deserialize_identifier
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 bytes byte_buf unit unit_struct tuple tuple_struct map
+        struct ignored_any
+    }
+}
+
+struct Values(std::vec::IntoIter<OsString>);
+
+impl<'de> SeqAccess<'de> for Values {
+    type Error = Error;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<Option<S::Value>, Error> {
+        match self.0.next() {
+            Some(value) => seed.deserialize(Value(value)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+/// A single value from the command line.
+struct Value(OsString);
+
+/// Turn a custom error from a visitor into [`Error::ParsingFailed`], so it
+/// mentions the value.
+fn in_value<T>(value: String, result: Result<T, Error>) -> Result<T, Error> {
+    result.map_err(|err| match err {
+        Error::Custom(error) => Error::ParsingFailed { value, error },
+        err => err,
+    })
+}
+
+macro_rules! parse_value {
+    ($($method:ident => $visit:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                visitor.$visit(self.0.parse()?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let value = self.0.string()?;
+        in_value(value.clone(), visitor.visit_string(value))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let value = self.0.string()?;
+        in_value(value.clone(), visitor.visit_enum(value.into_deserializer()))
+    }
+
+    parse_value! {
+        deserialize_bool => visit_bool
+        deserialize_i8 => visit_i8
+        deserialize_i16 => visit_i16
+        deserialize_i32 => visit_i32
+        deserialize_i64 => visit_i64
+        deserialize_i128 => visit_i128
+        deserialize_u8 => visit_u8
+        deserialize_u16 => visit_u16
+        deserialize_u32 => visit_u32
+        deserialize_u64 => visit_u64
+        deserialize_u128 => visit_u128
+        deserialize_f32 => visit_f32
+        deserialize_f64 => visit_f64
+        deserialize_char => visit_char
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct seq tuple tuple_struct map
+        struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::{Deserialize, Deserializer};
+    use std::fmt;
+
+    #[derive(Debug, PartialEq)]
+    enum Color {
+        Auto,
+        Never,
+    }
+
+    impl<'de> Deserialize<'de> for Color {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+            match String::deserialize(deserializer)?.as_str() {
+                "auto" => Ok(Color::Auto),
+                "never" => Ok(Color::Never),
+                other => Err(de::Error::unknown_variant(other, &["auto", "never"])),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Config {
+        verbose: bool,
+        dry_run: bool,
+        jobs: Option<u32>,
+        include: Vec<String>,
+        color: Option<Color>,
+        name: String,
+        x: bool,
+    }
+
+    // What #[derive(Deserialize)] would generate, more or less.
+    impl<'de> Deserialize<'de> for Config {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Config, D::Error> {
+            const FIELDS: &[&str] = &[
+                "verbose", "dry_run", "jobs", "include", "color", "name", "x",
+            ];
+
+            struct ConfigVisitor;
+
+            impl<'de> Visitor<'de> for ConfigVisitor {
+                type Value = Config;
+
+                fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str("struct Config")
+                }
+
+                fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Config, A::Error> {
+                    let mut verbose = None;
+                    let mut dry_run = None;
+                    let mut jobs = None;
+                    let mut include = None;
+                    let mut color = None;
+                    let mut name = None;
+                    let mut x = None;
+                    while let Some(key) = map.next_key::<String>()? {
+                        match key.as_str() {
+                            "verbose" => verbose = Some(map.next_value()?),
+                            "dry_run" => dry_run = Some(map.next_value()?),
+                            "jobs" => jobs = Some(map.next_value()?),
+                            "include" => include = Some(map.next_value()?),
+                            "color" => color = Some(map.next_value()?),
+                            "name" => name = Some(map.next_value()?),
+                            "x" => x = Some(map.next_value()?),
+                            _ => return Err(de::Error::unknown_field(&key, FIELDS)),
+                        }
+                    }
+                    Ok(Config {
+                        verbose: verbose.ok_or_else(|| de::Error::missing_field("verbose"))?,
+                        dry_run: dry_run.ok_or_else(|| de::Error::missing_field("dry_run"))?,
+                        jobs: jobs.unwrap_or(None),
+                        include: include.ok_or_else(|| de::Error::missing_field("include"))?,
+                        color: color.unwrap_or(None),
+                        name: name.ok_or_else(|| de::Error::missing_field("name"))?,
+                        x: x.ok_or_else(|| de::Error::missing_field("x"))?,
+                    })
+                }
+            }
+
+            deserializer.deserialize_struct("Config", FIELDS, ConfigVisitor)
+        }
+    }
+
+    fn parse(args: &[&str]) -> Result<Config, Error> {
+        from_parser(&mut Parser::from_args(args))
+    }
+
+    #[test]
+    fn fields() -> Result<(), Error> {
+        let config = parse(&[
+            "--name=app",
+            "--verbose",
+            "--include",
+            "a",
+            "--dry_run",
+            "-x",
+            "--jobs=4",
+            "--include=b",
+            "--color",
+            "never",
+        ])?;
+        assert_eq!(
+            config,
+            Config {
+                verbose: true,
+                dry_run: true,
+                jobs: Some(4),
+                include: vec!["a".into(), "b".into()],
+                color: Some(Color::Never),
+                name: "app".into(),
+                x: true,
+            }
+        );
+
+        let config = parse(&["--name=a", "--include", "a", "b", "--include=c", "-x"])?;
+        assert_eq!(config.include, ["a", "b", "c"]);
+        assert!(config.x);
+
+        let config = parse(&["--name", "a", "--dry-run", "--name", "b"])?;
+        assert_eq!(
+            config,
+            Config {
+                verbose: false,
+                dry_run: true,
+                jobs: None,
+                include: vec![],
+                color: None,
+                name: "b".into(),
+                x: false,
+            }
+        );
+
+        let mut parser = Parser::from_args(&["--name=a", "--verbose", "--no-verbose"]);
+        parser.set_negatable_options(&["verbose"]);
+        assert!(!from_parser::<Config>(&mut parser)?.verbose);
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        let err = parse(&["--name=a", "--jobs=four"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot parse argument \"four\": invalid digit found in string"
+        );
+        let err = parse(&["--name=a", "--color=always"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot parse argument \"always\": unknown variant `always`, expected `auto` or `never`"
+        );
+        let err = parse(&["--name=a", "--verbos"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid option '--verbos'; did you mean '--verbose'?"
+        );
+        assert_eq!(err.position().unwrap().index, 1);
+        let err = parse(&["--name=a", "-y"]).unwrap_err();
+        assert_eq!(err.to_string(), "invalid option '-y'");
+        let err = parse(&["--name=a", "file"]).unwrap_err();
+        assert_eq!(err.to_string(), "unexpected argument \"file\"");
+        let err = parse(&["--name=a", "--verbose=yes"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unexpected argument for option '--verbose': \"yes\""
+        );
+        let err = parse(&["--jobs", "1"]).unwrap_err();
+        assert_eq!(err.to_string(), "missing option '--name'");
+        let err = parse(&["--name"]).unwrap_err();
+        assert_eq!(err.to_string(), "missing argument for option '--name'");
+
+        let err = from_parser::<u32>(&mut Parser::from_args(&["1"])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "only structs can be deserialized from the command line"
+        );
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Renamed {
+        output: String,
+        level: u32,
+        accent: bool,
+    }
+
+    // What #[derive(Deserialize)] would generate for
+    // #[serde(rename = "out")] on output, #[serde(default)] on level
+    // and #[serde(rename = "é")] on accent.
+    impl<'de> Deserialize<'de> for Renamed {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Renamed, D::Error> {
+            const FIELDS: &[&str] = &["out", "level", "é"];
+
+            struct RenamedVisitor;
+
+            impl<'de> Visitor<'de> for RenamedVisitor {
+                type Value = Renamed;
+
+                fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str("struct Renamed")
+                }
+
+                fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Renamed, A::Error> {
+                    let mut output = None;
+                    let mut level = None;
+                    let mut accent = None;
+                    while let Some(key) = map.next_key::<String>()? {
+                        match key.as_str() {
+                            "out" => output = Some(map.next_value()?),
+                            "level" => level = Some(map.next_value()?),
+                            "é" => accent = Some(map.next_value()?),
+                            _ => return Err(de::Error::unknown_field(&key, FIELDS)),
+                        }
+                    }
+                    Ok(Renamed {
+                        output: output.ok_or_else(|| de::Error::missing_field("out"))?,
+                        level: level.unwrap_or_default(),
+                        accent: accent.ok_or_else(|| de::Error::missing_field("é"))?,
+                    })
+                }
+            }
+
+            deserializer.deserialize_struct("Renamed", FIELDS, RenamedVisitor)
+        }
+    }
+
+    #[test]
+    fn attributes() -> Result<(), Error> {
+        let renamed: Renamed = from_parser(&mut Parser::from_args(&["--out=a", "-é"]))?;
+        assert_eq!(
+            renamed,
+            Renamed {
+                output: "a".into(),
+                level: 0,
+                accent: true,
+            }
+        );
+        let renamed: Renamed = from_parser(&mut Parser::from_args(&["--level=3", "--out=b"]))?;
+        assert_eq!(renamed.level, 3);
+        assert!(!renamed.accent);
+
+        let err = from_parser::<Renamed>(&mut Parser::from_args(&["--output=a"])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid option '--output'; did you mean '--out'?"
+        );
+        let err = from_parser::<Renamed>(&mut Parser::from_args(&["--é"])).unwrap_err();
+        assert_eq!(err.to_string(), "invalid option '--é'");
+        let err = from_parser::<Renamed>(&mut Parser::from_args(&["--level=1"])).unwrap_err();
+        assert_eq!(err.to_string(), "missing option '--out'");
+        Ok(())
+    }
+}
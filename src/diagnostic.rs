@@ -0,0 +1,486 @@
+//! Multi-line rendering of errors.
+
+use std::{
+    borrow::Cow,
+    ffi::OsStr,
+    fmt::{self, Display},
+};
+
+#[cfg(windows)]
+use std::os::windows::ffi::OsStrExt;
+
+use crate::{shell, Error, Parser, Position};
+
+/// An [`Error`] rendered together with the command line it came from.
+///
+/// Created by [`Parser::diagnostic`]. This implements [`Display`]:
+/// ```text
+/// myapp: invalid option '-q'
+///   myapp -x -xq=foo 'hello world'
+///              ^
+/// Try 'myapp --help' for more information.
+/// ```
+///
+/// The first line is the error message, prefixed with the binary name and
+/// any subcommands (see [`Parser::command_name`]). If the parser kept a copy
+/// of the command line (see [`Parser::set_keep_command_line`]) and the error
+/// can be traced back to an argument, the command line is echoed (quoted for
+/// a POSIX shell) with the argument underlined.
+/// The usage line is only shown if one was given with [`Diagnostic::usage`].
+///
+/// Without [`color`](Diagnostic::color) the output only depends on the error
+/// and the command line, so it's suitable for snapshot tests. There is no
+/// trailing newline.
+///
+/// Arguments that aren't valid unicode are shown with replacement characters.
+/// The underline assumes that every character is one column wide.
+#[derive(Debug, Clone)]
+pub struct Diagnostic<'a> {
+    parser: &'a Parser,
+    error: &'a Error,
+    usage: Option<&'a str>,
+    color: bool,
+}
+
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const RESET: &str = "\x1b[0m";
+
+impl<'a> Diagnostic<'a> {
+    pub(crate) fn new(parser: &'a Parser, error: &'a Error) -> Diagnostic<'a> {
+        Diagnostic {
+            parser,
+            error,
+            usage: None,
+            color: false,
+        }
+    }
+
+    /// Add a line with usage information at the end.
+    pub fn usage(mut self, usage: &'a str) -> Diagnostic<'a> {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Highlight the output with ANSI escape codes.
+    ///
+    /// This is off by default. Deciding whether the terminal supports color
+    /// is up to you.
+    pub fn color(mut self, color: bool) -> Diagnostic<'a> {
+        self.color = color;
+        self
+    }
+
+    /// Figure out which argument to point at.
+    fn position(&self) -> Option<Position> {
+        if let Some(position) = self.parser.error_position(self.error) {
+            return self.argument(position.index).map(|_| position);
+        }
+        // These errors are typically returned right after the value was
+        // taken, but they can't know about it themselves.
+        let position = self.parser.current_position()?;
+        let text = display(self.argument(position.index)?);
+        let found = slice(&text, position.offset, position.len);
+        let matches = match *self.error {
+            Error::ParsingFailed { ref value, .. } => found == *value,
+            Error::NonUnicodeValue(ref value) => found == value.to_string_lossy(),
+            _ => false,
+        };
+        if matches {
+            Some(position)
+        } else {
+            None
+        }
+    }
+
+    /// The argument at an index, if the parser kept it.
+    fn argument(&self, index: usize) -> Option<&'a OsStr> {
+        let (first, ref args) = *self.parser.command_line.as_ref()?;
+        args.get(index.checked_sub(first)?)
+            .map(|arg| arg.as_os_str())
+    }
+
+    fn paint(&self, f: &mut fmt::Formatter<'_>, color: &str, text: &str) -> fmt::Result {
+        if self.color {
+            write!(f, "{}{}{}", color, text, RESET)
+        } else {
+            f.write_str(text)
+        }
+    }
+}
+
+impl Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = self.parser.command_name() {
+            self.paint(f, BOLD, &format!("{}:", name))?;
+            f.write_str(" ")?;
+        }
+        write!(f, "{}", self.error)?;
+
+        if let (Some(position), Some(&(first, ref args))) =
+            (self.position(), self.parser.command_line.as_ref())
+        {
+            let mut line = String::from("  ");
+            if first > 0 {
+                line.push_str("...");
+            }
+            let mut column = 0;
+            let mut width = 0;
+            for (index, arg) in (first..).zip(args) {
+                if index > 0 {
+                    line.push(' ');
+                }
+                let text = display(arg);
+                if index == position.index {
+                    let (before, span) = locate(&text, position.offset, position.len);
+                    column = line.chars().count() + before;
+                    width = span;
+                }
+                line.push_str(&quote(&text));
+            }
+            write!(f, "\n{}\n{}", line, " ".repeat(column))?;
+            // Always show something, even for an empty span.
+            self.paint(f, RED, &"^".repeat(width.max(1)))?;
+        }
+
+        if let Some(usage) = self.usage {
+            write!(f, "\n{}", usage)?;
+        }
+        Ok(())
+    }
+}
+
+impl Parser {
+    /// Keep a copy of the command line, so that [`Parser::diagnostic`] can
+    /// echo it. This is off by default, to avoid the copy.
+    ///
+    /// Call this right after creating the parser. Arguments that were
+    /// already taken can't be shown, so they're replaced by `...`.
+    /// Arguments added later by [`Parser::expand_response_files`] are kept.
+    pub fn set_keep_command_line(&mut self, on: bool) {
+        self.command_line = if on {
+            let start = self.end_index - self.source.len();
+            let mut args = Vec::new();
+            let first = match self.bin_name {
+                Some(ref bin_name) if start == 1 => {
+                    args.push(bin_name.into());
+                    0
+                }
+                _ => start,
+            };
+            args.extend(self.source.as_slice().iter().cloned());
+            Some((first, args))
+        } else {
+            None
+        };
+    }
+
+    /// Render an error as a multi-line message that points out the
+    /// offending argument in the command line.
+    ///
+    /// The argument is found with [`Parser::error_position`]. Errors from
+    /// [`ValueExt::parse`][crate::ValueExt::parse] point at the value the
+    /// parser just returned, if that's the value that failed.
+    ///
+    /// The command line is only echoed if
+    /// [`set_keep_command_line`][Parser::set_keep_command_line] was used.
+    ///
+    /// # Example
+    /// ```
+    /// # use lexopt::prelude::*;
+    /// let mut parser = lexopt::Parser::from_iter(&["myapp", "-x", "-xq=foo", "hello world"]);
+    /// parser.set_keep_command_line(true);
+    /// let err = loop {
+    ///     match parser.next() {
+    ///         Ok(Some(Short('x'))) => (),
+    ///         Ok(Some(arg)) => break arg.unexpected(),
+    ///         Ok(None) => unreachable!(),
+    ///         Err(err) => break err,
+    ///     }
+    /// };
+    /// let message = parser
+    ///     .diagnostic(&err)
+    ///     .usage("Try 'myapp --help' for more information.")
+    ///     .to_string();
+    /// assert_eq!(
+    ///     message,
+    ///     "\
+    /// myapp: invalid option '-q'
+    ///   myapp -x -xq=foo 'hello world'
+    ///              ^
+    /// Try 'myapp --help' for more information.",
+    /// );
+    /// ```
+    pub fn diagnostic<'a>(&'a self, error: &'a Error) -> Diagnostic<'a> {
+        Diagnostic::new(self, error)
+    }
+}
+
+/// Get the text of an argument, as it's indexed by a [`Position`].
+///
+/// On Windows positions count UTF-16 code units if the argument is not valid
+/// unicode, so we return those in that case.
+fn display(arg: &OsStr) -> Text<'_> {
+    #[cfg(windows)]
+    {
+        match arg.to_str() {
+            Some(text) => Text::Str(text),
+            None => Text::Wide(arg.encode_wide().collect()),
+        }
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        match arg.to_str() {
+            Some(text) => Text::Str(text),
+            None => Text::Bytes(arg.as_bytes()),
+        }
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        match arg.to_string_lossy() {
+            Cow::Borrowed(text) => Text::Str(text),
+            Cow::Owned(text) => Text::String(text),
+        }
+    }
+}
+
+enum Text<'a> {
+    Str(&'a str),
+    #[cfg(unix)]
+    Bytes(&'a [u8]),
+    #[cfg(windows)]
+    Wide(Vec<u16>),
+    #[cfg(not(any(unix, windows)))]
+    String(String),
+}
+
+impl Text<'_> {
+    fn lossy(&self) -> Cow<'_, str> {
+        match *self {
+            Text::Str(text) => Cow::Borrowed(text),
+            #[cfg(unix)]
+            Text::Bytes(bytes) => String::from_utf8_lossy(bytes),
+            #[cfg(windows)]
+            Text::Wide(ref units) => Cow::Owned(String::from_utf16_lossy(units)),
+            #[cfg(not(any(unix, windows)))]
+            Text::String(ref text) => Cow::Borrowed(text),
+        }
+    }
+}
+
+/// Decode part of an argument, clamping the range to the argument.
+fn slice<'a>(text: &'a Text<'_>, offset: usize, len: usize) -> Cow<'a, str> {
+    fn range<T>(items: &[T], offset: usize, len: usize) -> &[T] {
+        let start = offset.min(items.len());
+        let end = offset.saturating_add(len).min(items.len());
+        &items[start..end]
+    }
+    match *text {
+        Text::Str(text) => String::from_utf8_lossy(range(text.as_bytes(), offset, len)),
+        #[cfg(unix)]
+        Text::Bytes(bytes) => String::from_utf8_lossy(range(bytes, offset, len)),
+        #[cfg(windows)]
+        Text::Wide(ref units) => Cow::Owned(String::from_utf16_lossy(range(units, offset, len))),
+        #[cfg(not(any(unix, windows)))]
+        Text::String(ref text) => String::from_utf8_lossy(range(text.as_bytes(), offset, len)),
+    }
+}
+
+/// Find the column where a span starts in the quoted version of an argument,
+/// and how many columns it takes up.
+fn locate(text: &Text<'_>, offset: usize, len: usize) -> (usize, usize) {
+    let full = text.lossy();
+    let before = slice(text, 0, offset);
+    let span = slice(text, offset, len);
+    let quoted = needs_quotes(&full);
+    if before.is_empty() && span == full {
+        // Underline the whole thing, including any quotes.
+        return (0, quote(text).chars().count());
+    }
+    // Inside single quotes a quote takes up four columns: '\''
+    let width = |text: &str| {
+        text.chars()
+            .map(|ch| if quoted && ch == '\'' { 4 } else { 1 })
+            .sum::<usize>()
+    };
+    (quoted as usize + width(&before), width(&span))
+}
+
+fn needs_quotes(text: &str) -> bool {
+    shell::needs_quotes(text.as_bytes())
+}
+
+/// Quote an argument for a POSIX shell, if necessary.
+///
+/// This quotes the lossy version, unlike [`shell::quote`], so that the
+/// output is readable.
+fn quote(text: &Text<'_>) -> String {
+    shell::quote_str(&text.lossy())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use crate::prelude::*;
+    use crate::{Error, Parser};
+
+    fn render(parser: &Parser, err: &Error) -> String {
+        parser.diagnostic(err).to_string()
+    }
+
+    #[test]
+    fn rendering() -> Result<(), Error> {
+        let mut p = Parser::from_iter(&["app", "--it's", "x", "-abc", "--num=1x", "--num"]);
+        p.set_keep_command_line(true);
+        let err = p.next()?.unwrap().unexpected();
+        assert_eq!(
+            render(&p, &err),
+            "\
+app: invalid option '--it's'
+  app '--it'\\''s' x -abc --num=1x --num
+      ^^^^^^^^^^^",
+        );
+
+        let err = p.next()?.unwrap().unexpected();
+        assert_eq!(
+            render(&p, &err),
+            "\
+app: unexpected argument \"x\"
+  app '--it'\\''s' x -abc --num=1x --num
+                  ^",
+        );
+
+        p.next()?;
+        let err = p.next()?.unwrap().unexpected();
+        assert_eq!(
+            p.diagnostic(&err)
+                .color(true)
+                .usage("Usage: app")
+                .to_string(),
+            "\
+\x1b[1mapp:\x1b[0m invalid option '-b'
+  app '--it'\\''s' x -abc --num=1x --num
+                      \x1b[1;31m^\x1b[0m
+Usage: app",
+        );
+
+        p.next()?;
+        assert_eq!(p.next()?.unwrap(), Long("num"));
+        let err = p.value()?.parse::<u32>().unwrap_err();
+        assert_eq!(
+            render(&p, &err),
+            "\
+app: cannot parse argument \"1x\": invalid digit found in string
+  app '--it'\\''s' x -abc --num=1x --num
+                               ^^",
+        );
+
+        assert_eq!(p.next()?.unwrap(), Long("num"));
+        let err = p.value().unwrap_err();
+        assert_eq!(
+            render(&p, &err),
+            "\
+app: missing argument for option '--num'
+  app '--it'\\''s' x -abc --num=1x --num
+                                  ^^^^^",
+        );
+
+        // Not related to an argument
+        let err = Error::from("missing argument FILE");
+        assert_eq!(render(&p, &err), "app: missing argument FILE");
+        // Doesn't match the last argument
+        let err = OsString::from("abc").parse::<u32>().unwrap_err();
+        assert_eq!(
+            render(&p, &err),
+            "app: cannot parse argument \"abc\": invalid digit found in string"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn no_bin_name() -> Result<(), Error> {
+        let mut p = Parser::from_args(&["", "-x=y"]);
+        p.set_keep_command_line(true);
+        let err = p.next()?.unwrap().unexpected();
+        assert_eq!(
+            render(&p, &err),
+            "\
+unexpected argument \"\"
+  '' -x=y
+  ^^",
+        );
+
+        p.next()?;
+        let err = p.next().unwrap_err();
+        assert_eq!(
+            render(&p, &err),
+            "\
+unexpected argument for option '-x': \"y\"
+  '' -x=y
+        ^",
+        );
+
+        // An empty value still gets an arrow
+        let mut p = Parser::from_args(&["--x="]);
+        p.set_keep_command_line(true);
+        p.next()?;
+        let err = p.next().unwrap_err();
+        assert_eq!(
+            render(&p, &err),
+            "\
+unexpected argument for option '--x': \"\"
+  --x=
+      ^",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn keep_command_line() -> Result<(), Error> {
+        let mut p = Parser::from_iter(&["app", "-x", "y", "-z"]);
+        let err = p.next()?.unwrap().unexpected();
+        assert_eq!(render(&p, &err), "app: invalid option '-x'");
+
+        p.set_keep_command_line(true);
+        p.next()?;
+        let err = p.next()?.unwrap().unexpected();
+        assert_eq!(
+            render(&p, &err),
+            "\
+app: invalid option '-z'
+  ... y -z
+         ^",
+        );
+
+        let mut p = Parser::from_args(&["-x"]);
+        p.set_keep_command_line(true);
+        p.set_keep_command_line(false);
+        let err = p.next()?.unwrap().unexpected();
+        assert_eq!(render(&p, &err), "invalid option '-x'");
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn invalid_unicode() -> Result<(), Error> {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut p = Parser::from_args(&[OsStr::from_bytes(b"-\xFFx\xFF")]);
+        p.set_keep_command_line(true);
+        p.next()?;
+        let err = p.next()?.unwrap().unexpected();
+        assert_eq!(
+            render(&p, &err),
+            "\
+invalid option '-x'
+  '-\u{FFFD}x\u{FFFD}'
+     ^",
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,739 @@
+//! Parsing a command line described by a docopt-style usage text.
+//!
+//! Sometimes the help text is all the specification a program needs.
+//! [`Docopt`] reads a text with a `Usage:` line and a list of options, in
+//! the style of [docopt](http://docopt.org/), and parses a command line
+//! against it with [`Parser::next`] and [`Parser::value`]. The result is a
+//! [`Matches`] map from names like `--out` and `<input>` to values.
+//!
+//! Values stay [`OsString`]s, `--` ends the options as usual, and errors are
+//! ordinary [`Error`]s.
+//!
+//! This module requires the `docopt` feature.
+//!
+//! # Example
+//! ```
+//! # fn main() -> Result<(), lexopt::Error> {
+//! use lexopt::docopt::Docopt;
+//! use lexopt::prelude::*;
+//!
+//! const USAGE: &str = "
+//! Concatenate files.
+//!
+//! Usage: tool [-v] [--out=FILE] [--width=N] <input>...
+//!
+//! Options:
+//!   -v, --verbose     Print more output
+//!   -o, --out=FILE    Write to FILE [default: out.txt]
+//!   --width=N         Wrap lines at N columns
+//! ";
+//!
+//! let docopt = Docopt::new(USAGE);
+//! let mut parser = lexopt::Parser::from_args(&["a", "-v", "--wid", "80", "--", "-b"]);
+//! let matches = docopt.parse(&mut parser)?;
+//! assert!(matches.contains("--verbose"));
+//! assert_eq!(matches.get("-o").unwrap(), "out.txt");
+//! assert_eq!(matches.get("--width").unwrap().parse::<u32>()?, 80);
+//! assert_eq!(matches.get_all("<input>"), &["a", "-b"]);
+//!
+//! let mut parser = lexopt::Parser::from_args(&["-v"]);
+//! let err = docopt.parse(&mut parser).unwrap_err();
+//! assert_eq!(err.to_string(), "missing required argument '<input>'");
+//! # Ok(()) }
+//! ```
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+
+use crate::spec::{Command, Opt, Positional};
+use crate::{Arg, Error, Parser};
+
+/// A command line parser built from a usage text.
+///
+/// The text has a `Usage:` section with a single pattern, and may describe
+/// options in sections with a heading that ends with `options:`, like
+/// `Options:` or `Output options:`. Such a section continues as long as the
+/// lines are indented. Text before `Usage:` becomes the description of the
+/// [`command`](Docopt::command).
+///
+/// The pattern starts with the name of the program, followed by:
+/// - Options: `-v`, `--verbose`, `--out=FILE`, and `-o FILE` if the option
+///   list says that `-o` takes a value. Several short options can be
+///   combined, like `-abc`.
+/// - Positional arguments: `<input>` or `INPUT`.
+/// - `[...]` around options and positional arguments that are optional.
+///   Everything else is required.
+/// - `...` after an option or positional argument that may be repeated.
+/// - `[options]` to allow every option in the option list.
+/// - `[--]`, which is ignored since `--` always ends the options.
+///
+/// Alternatives with `|`, groups with `(...)` and commands aren't
+/// supported.
+///
+/// The option list describes options like this, with at least two spaces
+/// before the description:
+///
+/// ```text
+///   -o FILE, --out=FILE   Write to FILE [default: out.txt]
+/// ```
+///
+/// The names and value can be separated by spaces, commas and `=`.
+///
+/// Options are allowed if they're in the pattern, or if the pattern has
+/// `[options]`. Long options may be abbreviated.
+#[derive(Debug, Clone)]
+pub struct Docopt {
+    command: Command,
+    options: Vec<DocOption>,
+    positionals: Vec<DocPositional>,
+    /// Whether the pattern has `[options]`.
+    all_options: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+struct DocOption {
+    short: Option<char>,
+    long: Option<String>,
+    value: Option<String>,
+    default: Option<String>,
+    help: String,
+    /// Whether the option is in the pattern.
+    in_usage: bool,
+    required: bool,
+}
+
+impl DocOption {
+    /// The name that the option's values are stored under.
+    fn key(&self) -> String {
+        match self.long {
+            Some(ref long) => format!("--{}", long),
+            None => format!("-{}", self.short.unwrap()),
+        }
+    }
+
+    fn matches(&self, arg: &Arg<'_>) -> bool {
+        match *arg {
+            Arg::Short(ch) => self.short == Some(ch),
+            Arg::Long(name) => self.long.as_ref().map(String::as_str) == Some(name),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DocPositional {
+    name: String,
+    required: bool,
+    repeated: bool,
+}
+
+/// What a `...` in the pattern refers to.
+#[derive(Clone, Copy)]
+enum Element {
+    Option,
+    Positional(usize),
+}
+
+impl Docopt {
+    /// Read a usage text.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the text has no `Usage:` section, more than one pattern, or
+    /// syntax that isn't supported.
+    pub fn new(doc: &str) -> Docopt {
+        // ASCII lowercasing keeps the byte offsets intact.
+        let start = doc
+            .to_ascii_lowercase()
+            .find("usage:")
+            .expect("usage text has no 'Usage:'");
+        let about = doc[..start].trim();
+        let rest = &doc[start + "usage:".len()..];
+
+        let mut patterns = Vec::new();
+        for (i, line) in rest.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                if i == 0 {
+                    continue;
+                }
+                break;
+            }
+            patterns.push(line);
+        }
+        assert!(
+            patterns.len() == 1,
+            "usage text must have exactly one pattern"
+        );
+
+        let mut options: Vec<DocOption> = option_lines(rest)
+            .into_iter()
+            .filter(|line| is_option_line(line))
+            .map(parse_option_line)
+            .collect();
+
+        let mut tokens = tokenize(patterns[0]);
+        let program = tokens.remove(0);
+        let mut positionals: Vec<DocPositional> = Vec::new();
+        let mut all_options = false;
+        let mut optional = false;
+        let mut last = None;
+        let mut tokens = tokens.iter().map(String::as_str).peekable();
+        while let Some(token) = tokens.next() {
+            match token {
+                "[" => {
+                    assert!(!optional, "nested brackets are not supported");
+                    optional = true;
+                    last = None;
+                }
+                "]" => {
+                    assert!(optional, "unbalanced brackets in usage pattern");
+                    optional = false;
+                }
+                "..." => match last {
+                    Some(Element::Positional(index)) => positionals[index].repeated = true,
+                    // Options may always be repeated.
+                    Some(Element::Option) => (),
+                    None => panic!("'...' doesn't follow an argument"),
+                },
+                "options" if optional => {
+                    all_options = true;
+                    last = None;
+                }
+                "--" => last = None,
+                _ if token.starts_with("--") => {
+                    let mut parts = token[2..].splitn(2, '=');
+                    let name = parts.next().unwrap();
+                    let index = find_option(&mut options, |option| {
+                        option.long.as_ref().map(String::as_str) == Some(name)
+                    });
+                    let option = &mut options[index];
+                    option.long = Some(name.to_owned());
+                    if let Some(value) = parts.next() {
+                        option.value = Some(value.to_owned());
+                    }
+                    option.in_usage = true;
+                    option.required |= !optional;
+                    last = Some(Element::Option);
+                }
+                _ if token.starts_with('-') && token.len() > 1 => {
+                    let shorts = &token[1..];
+                    for (offset, ch) in shorts.char_indices() {
+                        let index = find_option(&mut options, |option| option.short == Some(ch));
+                        let option = &mut options[index];
+                        option.short = Some(ch);
+                        option.in_usage = true;
+                        option.required |= !optional;
+                        if option.value.is_some() {
+                            // Skip the value's name, as in -oFILE or -o FILE.
+                            let attached = offset + ch.len_utf8() < shorts.len();
+                            if !attached && tokens.peek().map_or(false, |&next| is_positional(next))
+                            {
+                                tokens.next();
+                            }
+                            break;
+                        }
+                    }
+                    last = Some(Element::Option);
+                }
+                _ if is_positional(token) => {
+                    positionals.push(DocPositional {
+                        name: token.to_owned(),
+                        required: !optional,
+                        repeated: false,
+                    });
+                    last = Some(Element::Positional(positionals.len() - 1));
+                }
+                _ => panic!("unsupported usage syntax {:?}", token),
+            }
+        }
+        assert!(!optional, "unbalanced brackets in usage pattern");
+
+        let mut command = Command::new(program).about(about);
+        for option in &options {
+            let mut opt = Opt::new().help(option.help.as_str());
+            if let Some(short) = option.short {
+                opt = opt.short(short);
+            }
+            if let Some(ref long) = option.long {
+                opt = opt.long(long.as_str());
+            }
+            if let Some(ref value) = option.value {
+                opt = opt.value(value.as_str());
+            }
+            if let Some(ref default) = option.default {
+                opt = opt.default_value(default.as_str());
+            }
+            command = command.option(opt);
+        }
+        for positional in &positionals {
+            let mut spec = Positional::new(positional.name.as_str());
+            if !positional.required {
+                spec = spec.optional();
+            }
+            if positional.repeated {
+                spec = spec.repeated();
+            }
+            command = command.positional(spec);
+        }
+
+        Docopt {
+            command,
+            options,
+            positionals,
+            all_options,
+        }
+    }
+
+    /// The command described by the usage text, for example to render help
+    /// or completion scripts.
+    pub fn command(&self) -> &Command {
+        &self.command
+    }
+
+    /// Parse the remaining arguments.
+    ///
+    /// This replaces the parser's [long options](Parser::set_long_options)
+    /// with the allowed options, so they can be abbreviated.
+    ///
+    /// Unknown options are reported as [`Error::UnexpectedOption`] (or
+    /// [`Error::MisspelledOption`] if they're close to a known one), values
+    /// for options that don't take one as [`Error::UnexpectedValue`], excess
+    /// positional arguments as [`Error::UnexpectedArgument`], and missing
+    /// required options and positional arguments as
+    /// [`Error::MissingArgument`].
+    pub fn parse(&self, parser: &mut Parser) -> Result<Matches, Error> {
+        let allowed: Vec<&DocOption> = self
+            .options
+            .iter()
+            .filter(|option| option.in_usage || self.all_options)
+            .collect();
+        let long_names: Vec<&str> = allowed
+            .iter()
+            .filter_map(|option| option.long.as_ref().map(String::as_str))
+            .collect();
+        parser.set_long_options(long_names.iter().cloned());
+
+        let mut matches = Matches::default();
+        for option in &self.options {
+            if let (Some(short), Some(_)) = (option.short, &option.long) {
+                matches.aliases.insert(format!("-{}", short), option.key());
+            }
+            matches.values.insert(option.key(), Vec::new());
+        }
+        for positional in &self.positionals {
+            matches.values.insert(positional.name.clone(), Vec::new());
+        }
+
+        // Without a repeated positional argument there's a limit, and it's
+        // checked right away so that the parser still points at the excess
+        // argument.
+        let max_values = if self
+            .positionals
+            .iter()
+            .any(|positional| positional.repeated)
+        {
+            None
+        } else {
+            Some(self.positionals.len())
+        };
+        let mut values = Vec::new();
+        while let Some(arg) = parser.next()? {
+            let found = match arg {
+                Arg::Value(value) => {
+                    if Some(values.len()) == max_values {
+                        return Err(Error::UnexpectedArgument(value));
+                    }
+                    values.push(value);
+                    continue;
+                }
+                _ => allowed.iter().find(|option| option.matches(&arg)),
+            };
+            let option = match found {
+                Some(option) => option,
+                None => {
+                    let error = arg.unexpected_among(&long_names);
+                    return Err(error.with_position(parser.current_position()));
+                }
+            };
+            let key = option.key();
+            if option.value.is_some() {
+                let value = parser.value()?;
+                matches.values.get_mut(&key).unwrap().push(value);
+            } else {
+                parser.reject_value()?;
+            }
+            *matches.counts.entry(key).or_insert(0) += 1;
+        }
+
+        let mut next = 0;
+        for (i, positional) in self.positionals.iter().enumerate() {
+            let needed_after = self.positionals[i + 1..]
+                .iter()
+                .filter(|positional| positional.required)
+                .count();
+            let available = (values.len() - next).saturating_sub(needed_after);
+            let take = if positional.repeated {
+                available
+            } else {
+                available.min(1)
+            };
+            if take == 0 && positional.required {
+                return Err(Error::MissingArgument {
+                    name: positional.name.clone(),
+                });
+            }
+            let taken = values[next..next + take].iter().cloned();
+            matches
+                .values
+                .get_mut(&positional.name)
+                .unwrap()
+                .extend(taken);
+            matches.counts.insert(positional.name.clone(), take);
+            next += take;
+        }
+        for option in &self.options {
+            let key = option.key();
+            if matches.count(&key) > 0 {
+                continue;
+            }
+            if option.required {
+                return Err(Error::MissingArgument { name: key });
+            }
+            if let Some(ref default) = option.default {
+                matches.values.insert(key, vec![default.into()]);
+            }
+        }
+
+        Ok(matches)
+    }
+}
+
+/// Find an option, or add it if it isn't in the option list.
+fn find_option(options: &mut Vec<DocOption>, pred: impl Fn(&DocOption) -> bool) -> usize {
+    match options.iter().position(pred) {
+        Some(index) => index,
+        None => {
+            options.push(DocOption::default());
+            options.len() - 1
+        }
+    }
+}
+
+/// The trimmed lines of the options sections. A section starts with a
+/// heading like `Options:` and ends at the first line that isn't indented.
+fn option_lines(text: &str) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut in_section = false;
+    for line in text.lines() {
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if in_section {
+                lines.push(line.trim());
+            }
+        } else {
+            in_section = line.trim_end().to_ascii_lowercase().ends_with("options:");
+        }
+    }
+    lines
+}
+
+/// Whether a line starts with an option name like `-o` or `--out`, as
+/// opposed to a list item like `- note`.
+fn is_option_line(line: &str) -> bool {
+    let name = if line.starts_with("--") {
+        &line[2..]
+    } else if line.starts_with('-') {
+        &line[1..]
+    } else {
+        return false;
+    };
+    match name.chars().next() {
+        Some(ch) => !ch.is_whitespace() && ch != ',' && ch != '=' && ch != '-',
+        None => false,
+    }
+}
+
+/// Parse a line like `-o FILE, --out=FILE  Write to FILE [default: x]`.
+fn parse_option_line(line: &str) -> DocOption {
+    let (names, help) = match line.find("  ") {
+        Some(index) => (&line[..index], line[index..].trim()),
+        None => (line, ""),
+    };
+    let mut option = DocOption::default();
+    let words = names
+        .split(|ch| ch == ' ' || ch == ',' || ch == '=')
+        .filter(|word| !word.is_empty());
+    for word in words {
+        if word.starts_with("--") {
+            option.long = Some(word[2..].to_owned());
+        } else if word.starts_with('-') {
+            let mut chars = word[1..].chars();
+            match (chars.next(), chars.next()) {
+                (Some(short), None) => option.short = Some(short),
+                _ => panic!("invalid option name {:?}", word),
+            }
+        } else {
+            option.value = Some(word.to_owned());
+        }
+    }
+
+    let mut help = help.to_owned();
+    if option.value.is_some() {
+        if let Some(start) = help.to_ascii_lowercase().find("[default:") {
+            if let Some(len) = help[start..].find(']') {
+                let default = help[start + "[default:".len()..start + len].trim();
+                option.default = Some(default.to_owned());
+                help = format!("{}{}", &help[..start], &help[start + len + 1..])
+                    .trim()
+                    .to_owned();
+            }
+        }
+    }
+    option.help = help;
+    option
+}
+
+/// Split a pattern into words, brackets and ellipses.
+fn tokenize(pattern: &str) -> Vec<String> {
+    let mut spaced = String::new();
+    let mut rest = pattern;
+    while let Some(ch) = rest.chars().next() {
+        if rest.starts_with("...") {
+            spaced.push_str(" ... ");
+            rest = &rest[3..];
+            continue;
+        }
+        match ch {
+            '[' | ']' | '(' | ')' | '|' => {
+                spaced.push(' ');
+                spaced.push(ch);
+                spaced.push(' ');
+            }
+            _ => spaced.push(ch),
+        }
+        rest = &rest[ch.len_utf8()..];
+    }
+    spaced.split_whitespace().map(str::to_owned).collect()
+}
+
+/// Whether a pattern word is a positional argument, like `<file>` or `FILE`.
+fn is_positional(word: &str) -> bool {
+    (word.starts_with('<') && word.ends_with('>') && word.len() > 2)
+        || (word.chars().any(|ch| ch.is_ascii_uppercase())
+            && word
+                .chars()
+                .all(|ch| ch.is_ascii_uppercase() || ch.is_ascii_digit() || ch == '-' || ch == '_'))
+}
+
+/// The values found by [`Docopt::parse`], by name.
+///
+/// Options are stored under their long name if they have one, like
+/// `--out`, and otherwise under their short name, like `-v`. The short name
+/// of an option with a long name can be used to look it up as well.
+/// Positional arguments are stored under the name in the pattern, like
+/// `<input>` or `INPUT`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Matches {
+    values: BTreeMap<String, Vec<OsString>>,
+    counts: BTreeMap<String, usize>,
+    /// Short names of options that also have a long name.
+    aliases: BTreeMap<String, String>,
+}
+
+impl Matches {
+    fn key<'a>(&'a self, name: &'a str) -> &'a str {
+        self.aliases.get(name).map_or(name, String::as_str)
+    }
+
+    /// The last value of an option or positional argument.
+    ///
+    /// This is the default value if the option wasn't given.
+    pub fn get(&self, name: &str) -> Option<&OsString> {
+        self.get_all(name).last()
+    }
+
+    /// All values of an option or positional argument.
+    pub fn get_all(&self, name: &str) -> &[OsString] {
+        self.values
+            .get(self.key(name))
+            .map_or(&[], |values| values.as_slice())
+    }
+
+    /// How many times an option or positional argument was given.
+    pub fn count(&self, name: &str) -> usize {
+        self.counts.get(self.key(name)).cloned().unwrap_or(0)
+    }
+
+    /// Whether an option or positional argument was given.
+    pub fn contains(&self, name: &str) -> bool {
+        self.count(name) > 0
+    }
+
+    /// The values of all options and positional arguments. Options without
+    /// a value have an empty list.
+    pub fn values(&self) -> &BTreeMap<String, Vec<OsString>> {
+        &self.values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    const USAGE: &str = "
+Copy files.
+
+usage:
+  cp [options] [-r] <source>... <dest>
+
+Options:
+  -f, --force           Overwrite files
+  -v --verbose          Explain what is being done
+  -S SUFFIX, --suffix=SUFFIX  Backup suffix [default: ~]
+  --mode MODE           Permissions
+";
+
+    fn parse(usage: &str, args: &[&str]) -> Result<Matches, Error> {
+        Docopt::new(usage).parse(&mut Parser::from_args(args))
+    }
+
+    #[test]
+    fn options() -> Result<(), Error> {
+        let matches = parse(USAGE, &["-vvf", "a", "--suf", ".bak", "b", "-r", "c"])?;
+        assert_eq!(matches.count("--verbose"), 2);
+        assert_eq!(matches.count("-v"), 2);
+        assert!(matches.contains("-f"));
+        assert!(matches.contains("-r"));
+        assert!(!matches.contains("--mode"));
+        assert_eq!(matches.get("--suffix").unwrap(), ".bak");
+        assert_eq!(matches.get_all("<source>"), &["a", "b"]);
+        assert_eq!(matches.get("<dest>").unwrap(), "c");
+        assert_eq!(matches.get("--mode"), None);
+        assert_eq!(matches.get("--nonexistent"), None);
+        assert_eq!(
+            matches.values().keys().collect::<Vec<_>>(),
+            &[
+                "--force",
+                "--mode",
+                "--suffix",
+                "--verbose",
+                "-r",
+                "<dest>",
+                "<source>"
+            ]
+        );
+
+        let matches = parse(USAGE, &["a", "b", "--mode=644"])?;
+        assert_eq!(matches.get("-S").unwrap(), "~");
+        assert_eq!(matches.count("-S"), 0);
+        assert_eq!(matches.get("--mode").unwrap(), "644");
+        Ok(())
+    }
+
+    #[test]
+    fn positionals() -> Result<(), Error> {
+        let usage = "Usage: app <a> [<b>] [C...] <d>";
+        let matches = parse(usage, &["1", "2"])?;
+        assert_eq!(matches.get("<a>").unwrap(), "1");
+        assert!(!matches.contains("<b>"));
+        assert_eq!(matches.get("<d>").unwrap(), "2");
+        let matches = parse(usage, &["1", "2", "3", "4", "5"])?;
+        assert_eq!(matches.get("<b>").unwrap(), "2");
+        assert_eq!(matches.get_all("C"), &["3", "4"]);
+        assert_eq!(matches.count("C"), 2);
+        assert_eq!(matches.get("<d>").unwrap(), "5");
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        let err = parse(USAGE, &["a"]).unwrap_err();
+        assert_eq!(err.to_string(), "missing required argument '<source>'");
+        let err = parse(USAGE, &["a", "b", "--verbos=x"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unexpected argument for option '--verbose': \"x\""
+        );
+        let err = parse(USAGE, &["a", "b", "--verbs"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid option '--verbs'; did you mean '--verbose'?"
+        );
+        assert_eq!(
+            err.position(),
+            Some(Position {
+                index: 2,
+                offset: 0,
+                len: 7
+            })
+        );
+        let err = parse(USAGE, &["a", "b", "--suffix"]).unwrap_err();
+        assert_eq!(err.to_string(), "missing argument for option '--suffix'");
+
+        let usage = "Usage: app --out=FILE [-v] [<input>]
+
+Options:
+  -v  Be verbose
+  -q  Be quiet
+";
+        let mut parser = Parser::from_args(&["--out", "x", "y", "z", "-v"]);
+        let err = Docopt::new(usage).parse(&mut parser).unwrap_err();
+        assert_eq!(err.to_string(), "unexpected argument \"z\"");
+        assert_eq!(parser.error_position(&err).unwrap().index, 3);
+        let err = parse(usage, &["-v"]).unwrap_err();
+        assert_eq!(err.to_string(), "missing required argument '--out'");
+        let err = parse(usage, &["--out=x", "-q"]).unwrap_err();
+        assert_eq!(err.to_string(), "invalid option '-q'");
+    }
+
+    #[test]
+    fn command() {
+        let docopt = Docopt::new(USAGE);
+        let command = docopt.command();
+        assert_eq!(command.name(), "cp");
+        assert_eq!(command.usage(), "Usage: cp [OPTIONS] <source>... <dest>");
+        let suffix = &command.options()[2];
+        assert_eq!(suffix.get_short(), Some('S'));
+        assert_eq!(suffix.get_long(), Some("suffix"));
+        assert_eq!(suffix.get_value(), Some("SUFFIX"));
+        assert_eq!(suffix.get_help(), "Backup suffix");
+        assert_eq!(suffix.get_default_value(), Some("~"));
+        assert_eq!(command.options()[4].get_short(), Some('r'));
+    }
+
+    #[test]
+    fn prose() -> Result<(), Error> {
+        let usage = "
+Usage: app [options]
+
+Options:
+  -q, --quiet  Be quiet
+
+  - lists in options sections are fine
+
+Notes:
+  - notes can be lists
+  -- or have dashes
+  ---
+  -foo is odd
+  -1 means unlimited
+-x on its own line
+";
+        assert_eq!(Docopt::new(usage).command().options().len(), 1);
+        assert_eq!(parse(usage, &["-q"])?.count("--quiet"), 1);
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic]
+    fn alternatives() {
+        Docopt::new("Usage: app (-a | -b)");
+    }
+
+    #[test]
+    #[should_panic]
+    fn commands() {
+        Docopt::new("Usage: app run <file>");
+    }
+}
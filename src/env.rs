@@ -0,0 +1,440 @@
+//! Reading options from environment variables.
+//!
+//! [`Fallback`] wraps a [`Parser`] and supplies options from environment
+//! variables if they weren't given on the command line.
+
+use std::{
+    ffi::{OsStr, OsString},
+    ops::Deref,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{Arg, Error, Parser, ValueExt};
+
+/// Where a [`Value`] came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// The command line.
+    CommandLine,
+    /// An environment variable with this name.
+    Env(String),
+}
+
+/// A value returned by [`Fallback`], which remembers where it came from.
+///
+/// It implements [`ValueExt`], and errors from its methods mention the
+/// environment variable if there was one:
+/// ```text
+/// in $MYAPP_JOBS: cannot parse argument "x": invalid digit found in string
+/// ```
+///
+/// It dereferences to [`OsStr`]. Use [`into_os_string`](Value::into_os_string)
+/// or `From` to get an [`OsString`] or [`PathBuf`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Value {
+    value: OsString,
+    source: Source,
+}
+
+impl Value {
+    /// Where the value came from.
+    pub fn source(&self) -> &Source {
+        &self.source
+    }
+
+    /// Convert into a plain [`OsString`].
+    pub fn into_os_string(self) -> OsString {
+        self.value
+    }
+
+    /// Add the environment variable to an error, if the value came from one.
+    fn context(&self, error: Error) -> Error {
+        match self.source {
+            Source::CommandLine => error,
+            Source::Env(ref var) => Error::Env {
+                var: var.clone(),
+                error: Box::new(error),
+            },
+        }
+    }
+}
+
+impl Deref for Value {
+    type Target = OsStr;
+
+    fn deref(&self) -> &OsStr {
+        &self.value
+    }
+}
+
+impl AsRef<OsStr> for Value {
+    fn as_ref(&self) -> &OsStr {
+        &self.value
+    }
+}
+
+impl AsRef<Path> for Value {
+    fn as_ref(&self) -> &Path {
+        self.value.as_ref()
+    }
+}
+
+impl From<Value> for OsString {
+    fn from(value: Value) -> OsString {
+        value.value
+    }
+}
+
+impl From<Value> for PathBuf {
+    fn from(value: Value) -> PathBuf {
+        value.value.into()
+    }
+}
+
+impl ValueExt for Value {
+    fn parse<T: FromStr>(&self) -> Result<T, Error>
+    where
+        T::Err: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    {
+        self.value.parse().map_err(|err| self.context(err))
+    }
+
+    fn parse_with<F, T, E>(&self, func: F) -> Result<T, Error>
+    where
+        F: FnOnce(&str) -> Result<T, E>,
+        E: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    {
+        self.value.parse_with(func).map_err(|err| self.context(err))
+    }
+
+    fn string(self) -> Result<String, Error> {
+        match self.value.into_string() {
+            Ok(string) => Ok(string),
+            Err(raw) => {
+                let error = Error::NonUnicodeValue(raw);
+                Err(match self.source {
+                    Source::CommandLine => error,
+                    Source::Env(var) => Error::Env {
+                        var,
+                        error: Box::new(error),
+                    },
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Var {
+    name: String,
+    /// The spellings of the option, like `--token` and `-t`. The first
+    /// one is used if we read the variable.
+    options: Vec<String>,
+    /// Whether the option was given on the command line.
+    seen: bool,
+}
+
+/// A [`Parser`] that falls back to environment variables for options that
+/// weren't given on the command line.
+///
+/// It's used in the same way as a `Parser`. Once the command line runs out
+/// [`next()`](Fallback::next) returns the options that weren't found but
+/// whose environment variable is set, in the order they were registered.
+/// The variable's value is then returned by [`value()`](Fallback::value).
+///
+/// Variables are read with [`std::env::var_os`], so their values don't have
+/// to be valid unicode. Variables that are set to an empty string are
+/// ignored.
+///
+/// # Example
+/// ```
+/// # fn main() -> Result<(), lexopt::Error> {
+/// use lexopt::prelude::*;
+///
+/// # std::env::set_var("MYAPP_TOKEN", "hunter2");
+/// let parser = lexopt::Parser::from_iter(&["myapp", "-j", "4"]);
+/// let mut parser = lexopt::env::Fallback::new(parser);
+/// parser.var("MYAPP_TOKEN", &["--token", "-t"]);
+/// parser.var("MYAPP_JOBS", &["--jobs", "-j"]);
+///
+/// let mut token = None;
+/// let mut jobs = 1;
+/// while let Some(arg) = parser.next()? {
+///     match arg {
+///         Short('t') | Long("token") => token = Some(parser.value()?.string()?),
+///         Short('j') | Long("jobs") => jobs = parser.value()?.parse()?,
+///         _ => return Err(arg.unexpected()),
+///     }
+/// }
+/// assert_eq!(token.unwrap(), "hunter2");
+/// assert_eq!(jobs, 4);
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Fallback {
+    parser: Parser,
+    vars: Vec<Var>,
+    /// Whether `parser` ran out.
+    finished: bool,
+    /// The next variable to check once `parser` has run out.
+    cursor: usize,
+    /// The value of the variable we just emitted an option for.
+    pending: Option<(usize, OsString)>,
+}
+
+impl Fallback {
+    /// Wrap a parser.
+    pub fn new(parser: Parser) -> Fallback {
+        Fallback {
+            parser,
+            vars: Vec::new(),
+            finished: false,
+            cursor: 0,
+            pending: None,
+        }
+    }
+
+    /// Register an environment variable for an option.
+    ///
+    /// `options` lists the ways the option can be spelled, including dashes,
+    /// like `&["--token", "-t"]`. If none of them are found on the command
+    /// line the first one is returned instead. Options with a plus sign, like
+    /// `+t`, can't have a fallback.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `options` is empty or contains something that doesn't look
+    /// like an option.
+    pub fn var(&mut self, var: &str, options: &[&str]) {
+        assert!(!options.is_empty(), "no options given for {}", var);
+        for option in options {
+            assert!(
+                (option.starts_with("--") && option.len() > 2)
+                    || (option.starts_with('-') && option.chars().count() == 2),
+                "{:?} is not an option",
+                option
+            );
+        }
+        self.vars.push(Var {
+            name: var.to_owned(),
+            options: options.iter().map(|&option| option.to_owned()).collect(),
+            seen: false,
+        });
+    }
+
+    /// Get the next option or positional argument, as by [`Parser::next`].
+    ///
+    /// # Errors
+    ///
+    /// In addition to the errors from [`Parser::next`], this returns an
+    /// [`Error::Env`] if the last option came from an environment variable
+    /// and the value wasn't used.
+    pub fn next(&mut self) -> Result<Option<Arg<'_>>, Error> {
+        if let Some((index, value)) = self.pending.take() {
+            let var = &self.vars[index];
+            return Err(Error::Env {
+                var: var.name.clone(),
+                error: Box::new(Error::UnexpectedValue {
+                    option: var.options[0].clone(),
+                    value,
+                }),
+            });
+        }
+
+        if !self.finished {
+            match self.parser.next()? {
+                Some(arg) => {
+                    for var in &mut self.vars {
+                        if var.options.iter().any(|option| matches(option, &arg)) {
+                            var.seen = true;
+                        }
+                    }
+                    return Ok(Some(arg));
+                }
+                None => self.finished = true,
+            }
+        }
+
+        while self.cursor < self.vars.len() {
+            let index = self.cursor;
+            self.cursor += 1;
+            let var = &self.vars[index];
+            if var.seen {
+                continue;
+            }
+            match std::env::var_os(&var.name) {
+                Some(ref value) if value.is_empty() => (),
+                Some(value) => {
+                    self.pending = Some((index, value));
+                    let option = &self.vars[index].options[0];
+                    return Ok(Some(if option.starts_with("--") {
+                        Arg::Long(&option[2..])
+                    } else {
+                        Arg::Short(option[1..].chars().next().unwrap())
+                    }));
+                }
+                None => (),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Get a value for an option, as by [`Parser::value`].
+    ///
+    /// If the option came from an environment variable then this returns
+    /// the variable's value.
+    pub fn value(&mut self) -> Result<Value, Error> {
+        if let Some((index, value)) = self.pending.take() {
+            return Ok(Value {
+                value,
+                source: Source::Env(self.vars[index].name.clone()),
+            });
+        }
+        Ok(Value {
+            value: self.parser.value()?,
+            source: Source::CommandLine,
+        })
+    }
+
+    /// Get a value only if it's concatenated to an option, as by
+    /// [`Parser::optional_value`].
+    ///
+    /// If the option came from an environment variable then this returns
+    /// the variable's value.
+    pub fn optional_value(&mut self) -> Option<Value> {
+        if let Some((index, value)) = self.pending.take() {
+            return Some(Value {
+                value,
+                source: Source::Env(self.vars[index].name.clone()),
+            });
+        }
+        Some(Value {
+            value: self.parser.optional_value()?,
+            source: Source::CommandLine,
+        })
+    }
+
+    /// Access the underlying parser, e.g. to call [`Parser::values`].
+    ///
+    /// Options from environment variables are not visible through the parser.
+    pub fn parser(&mut self) -> &mut Parser {
+        &mut self.parser
+    }
+
+    /// Get the underlying parser back.
+    pub fn into_inner(self) -> Parser {
+        self.parser
+    }
+}
+
+/// Check whether an option spelled like `--foo` or `-f` matches an argument.
+fn matches(option: &str, arg: &Arg<'_>) -> bool {
+    match *arg {
+        Arg::Long(name) => option.starts_with("--") && option[2..] == *name,
+        Arg::Short(ch) => {
+            let mut chars = option.chars();
+            chars.next() == Some('-') && chars.next() == Some(ch) && chars.next().is_none()
+        }
+        Arg::Plus(_) | Arg::PlusLong(_) | Arg::Value(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    fn fallback(args: &[&str]) -> Fallback {
+        let mut parser = Fallback::new(Parser::from_args(args));
+        parser.var("LEXOPT_TEST_NUMBER", &["--number", "-n"]);
+        parser.var("LEXOPT_TEST_UNSET", &["--unset"]);
+        parser.var("LEXOPT_TEST_EMPTY", &["--empty"]);
+        parser.var("LEXOPT_TEST_SHORT", &["-s"]);
+        parser
+    }
+
+    #[test]
+    fn env_fallback() -> Result<(), Error> {
+        std::env::set_var("LEXOPT_TEST_NUMBER", "12");
+        std::env::set_var("LEXOPT_TEST_EMPTY", "");
+        std::env::set_var("LEXOPT_TEST_SHORT", "x");
+        std::env::remove_var("LEXOPT_TEST_UNSET");
+
+        let mut p = fallback(&["a", "-n3"]);
+        assert_eq!(p.next()?.unwrap(), Value("a".into()));
+        assert_eq!(p.next()?.unwrap(), Short('n'));
+        let value = p.value()?;
+        assert_eq!(value.source(), &Source::CommandLine);
+        assert_eq!(value.parse::<u32>()?, 3);
+        assert_eq!(p.next()?.unwrap(), Short('s'));
+        assert_eq!(p.value()?.into_os_string(), "x");
+        assert_eq!(p.next()?, None);
+        assert_eq!(p.next()?, None);
+
+        let mut p = fallback(&["-s", "y", "--", "-n"]);
+        assert_eq!(p.next()?.unwrap(), Short('s'));
+        assert_eq!(p.value()?.into_os_string(), "y");
+        assert_eq!(p.next()?.unwrap(), Value("-n".into()));
+        assert_eq!(p.next()?.unwrap(), Long("number"));
+        let value = p.value()?;
+        assert_eq!(value.source(), &Source::Env("LEXOPT_TEST_NUMBER".into()));
+        assert_eq!(value.parse::<u32>()?, 12);
+        assert_eq!(
+            value
+                .parse_with(|_| Err::<(), _>("too big"))
+                .unwrap_err()
+                .to_string(),
+            "in $LEXOPT_TEST_NUMBER: cannot parse argument \"12\": too big",
+        );
+        assert_eq!(p.next()?, None);
+
+        // Unused values are an error
+        let mut p = fallback(&["-n", "1"]);
+        p.next()?;
+        p.value()?;
+        assert_eq!(p.next()?.unwrap(), Short('s'));
+        assert_eq!(
+            p.next().unwrap_err().to_string(),
+            "in $LEXOPT_TEST_SHORT: unexpected argument for option '-s': \"x\"",
+        );
+        assert_eq!(p.next()?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn command_line_errors_unchanged() {
+        let mut p = Fallback::new(Parser::from_args(&["-n", "x"]));
+        p.next().unwrap();
+        let err = p.value().unwrap().parse::<u32>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot parse argument \"x\": invalid digit found in string",
+        );
+        let mut p = Fallback::new(Parser::from_args(&["-n"]));
+        p.next().unwrap();
+        assert_eq!(
+            p.value().unwrap_err().to_string(),
+            "missing argument for option '-n'",
+        );
+        let mut parser = Parser::from_args(&["-n=3"]);
+        parser.set_getopt_optional_values(true);
+        let mut p = Fallback::new(parser);
+        p.next().unwrap();
+        assert_eq!(p.value().unwrap().into_os_string(), "3");
+    }
+
+    #[test]
+    fn matching() {
+        assert!(matches("--foo", &Long("foo")));
+        assert!(!matches("--foo", &Long("fo")));
+        assert!(!matches("-f", &Long("f")));
+        assert!(matches("-f", &Short('f')));
+        assert!(matches("-µ", &Short('µ')));
+        assert!(!matches("--f", &Short('f')));
+        assert!(!matches("-f", &Value("-f".into())));
+        assert!(!matches("-f", &Plus('f')));
+        assert!(!matches("--foo", &PlusLong("foo")));
+    }
+}
@@ -0,0 +1,254 @@
+//! Parsing short options described by a POSIX `getopt` optstring.
+//!
+//! [`Getopt`] wraps a [`Parser`] for programs ported from C that describe
+//! their options like `getopt(3)` does, with a string such as `"ab:c::"`.
+
+use std::ffi::OsString;
+
+use crate::{Arg, Error, Parser};
+
+/// An option or positional argument returned by [`Getopt::next`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// An option without a value, like `a` in `"ab:c::"`.
+    Flag(char),
+    /// An option with a required value, like `b:`. The value was given as
+    /// `-bvalue` or `-b value`.
+    WithValue(char, OsString),
+    /// An option with an optional value, like `c::`. The value has to be
+    /// attached, as in `-cvalue`.
+    WithOptionalValue(char, Option<OsString>),
+    /// A positional argument.
+    Positional(OsString),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Flag,
+    Required,
+    Optional,
+}
+
+/// A parser for the short options in a `getopt` optstring.
+///
+/// Each character in the optstring is an option. A single colon after it
+/// means it takes a value, two colons mean the value is optional and must be
+/// attached. Values are taken the way `getopt` takes them, so `-b=x` has
+/// the value `=x`.
+///
+/// Like GNU's `getopt`, positional arguments can come before options, unless
+/// the optstring starts with `+`. Then the first positional argument and
+/// everything after it are positional arguments. A leading `-` or `:` is
+/// accepted for compatibility but makes no difference, since errors are
+/// returned instead of printed.
+///
+/// Unknown options are reported as [`Error::InvalidOption`] and missing
+/// values as [`Error::RequiresArgument`], with the same messages as
+/// `getopt`. Long options are reported as [`Error::UnexpectedOption`].
+///
+/// # Example
+/// ```
+/// # fn main() -> Result<(), lexopt::Error> {
+/// use lexopt::getopt::{Event, Getopt};
+///
+/// let parser = lexopt::Parser::from_args(&["-ab", "x", "file", "-cy", "-c"]);
+/// let mut getopt = Getopt::new(parser, "ab:c::");
+/// assert_eq!(getopt.next()?, Some(Event::Flag('a')));
+/// assert_eq!(getopt.next()?, Some(Event::WithValue('b', "x".into())));
+/// assert_eq!(getopt.next()?, Some(Event::Positional("file".into())));
+/// assert_eq!(getopt.next()?, Some(Event::WithOptionalValue('c', Some("y".into()))));
+/// assert_eq!(getopt.next()?, Some(Event::WithOptionalValue('c', None)));
+/// assert_eq!(getopt.next()?, None);
+///
+/// let parser = lexopt::Parser::from_args(&["-x"]);
+/// let err = Getopt::new(parser, "ab:c::").next().unwrap_err();
+/// assert_eq!(err.to_string(), "invalid option -- 'x'");
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Getopt {
+    parser: Parser,
+    options: Vec<(char, Kind)>,
+    /// Whether the first positional argument ends the options.
+    stop_at_positional: bool,
+    /// Whether such a positional argument was found.
+    finished: bool,
+}
+
+impl Getopt {
+    /// Wrap a parser, with the options described by `optstring`.
+    ///
+    /// This disables [`short_equals`](Parser::set_short_equals) on the
+    /// parser.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `optstring` has a colon that doesn't follow an option, or
+    /// more than two colons in a row.
+    pub fn new(mut parser: Parser, optstring: &str) -> Getopt {
+        parser.set_short_equals(false);
+        let mut stop_at_positional = false;
+        let mut chars = optstring.chars().peekable();
+        while let Some(&ch) = chars.peek() {
+            match ch {
+                '+' => stop_at_positional = true,
+                '-' | ':' => (),
+                _ => break,
+            }
+            chars.next();
+        }
+
+        let mut options = Vec::new();
+        while let Some(ch) = chars.next() {
+            assert!(ch != ':', "invalid optstring {:?}", optstring);
+            let mut colons = 0;
+            while chars.peek() == Some(&':') {
+                chars.next();
+                colons += 1;
+            }
+            let kind = match colons {
+                0 => Kind::Flag,
+                1 => Kind::Required,
+                2 => Kind::Optional,
+                _ => panic!("invalid optstring {:?}", optstring),
+            };
+            if !options.iter().any(|&(known, _)| known == ch) {
+                options.push((ch, kind));
+            }
+        }
+
+        Getopt {
+            parser,
+            options,
+            stop_at_positional,
+            finished: false,
+        }
+    }
+
+    /// Get the next option or positional argument, with its value.
+    ///
+    /// A return value of `Ok(None)` means the command line has been exhausted.
+    pub fn next(&mut self) -> Result<Option<Event>, Error> {
+        if self.finished {
+            return Ok(self.parser.raw_args()?.next().map(Event::Positional));
+        }
+        let option = match self.parser.next()? {
+            None => return Ok(None),
+            Some(Arg::Short(option)) => option,
+            Some(Arg::Value(value)) => {
+                self.finished = self.stop_at_positional;
+                return Ok(Some(Event::Positional(value)));
+            }
+            Some(arg) => return Err(arg.unexpected()),
+        };
+        let position = self.parser.current_position();
+        let kind = self
+            .options
+            .iter()
+            .find(|&&(known, _)| known == option)
+            .map(|&(_, kind)| kind);
+        match kind {
+            Some(Kind::Flag) => Ok(Some(Event::Flag(option))),
+            Some(Kind::Required) => match self.parser.value() {
+                Ok(value) => Ok(Some(Event::WithValue(option, value))),
+                Err(Error::MissingValue { .. }) => {
+                    Err(Error::RequiresArgument { option, position })
+                }
+                Err(err) => Err(err),
+            },
+            Some(Kind::Optional) => Ok(Some(Event::WithOptionalValue(
+                option,
+                self.parser.optional_value(),
+            ))),
+            None => Err(Error::InvalidOption { option, position }),
+        }
+    }
+
+    /// Get a mutable reference to the underlying parser, for example to
+    /// render a [`diagnostic`](Parser::diagnostic).
+    pub fn parser(&mut self) -> &mut Parser {
+        &mut self.parser
+    }
+
+    /// Get the underlying parser back.
+    pub fn into_inner(self) -> Parser {
+        self.parser
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    fn events(optstring: &str, args: &[&str]) -> Result<Vec<Event>, Error> {
+        let mut getopt = Getopt::new(Parser::from_args(args), optstring);
+        let mut events = Vec::new();
+        while let Some(event) = getopt.next()? {
+            events.push(event);
+        }
+        Ok(events)
+    }
+
+    #[test]
+    fn parsing() -> Result<(), Error> {
+        use self::Event::*;
+        assert_eq!(
+            events(
+                "ab:c::",
+                &["-a", "x", "-b=1", "-ab", "-", "-cac", "--", "-a"]
+            )?,
+            &[
+                Flag('a'),
+                Positional("x".into()),
+                WithValue('b', "=1".into()),
+                Flag('a'),
+                WithValue('b', "-".into()),
+                WithOptionalValue('c', Some("ac".into())),
+                Positional("-a".into()),
+            ]
+        );
+        assert_eq!(
+            events("+a", &["-a", "x", "-a", "--", "y"])?,
+            &[
+                Flag('a'),
+                Positional("x".into()),
+                Positional("-a".into()),
+                Positional("--".into()),
+                Positional("y".into()),
+            ]
+        );
+        assert_eq!(
+            events("-:a", &["x", "-a"])?,
+            &[Positional("x".into()), Flag('a')]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        let err = events("ab:", &["-a", "-xa"]).unwrap_err();
+        assert_eq!(err.to_string(), "invalid option -- 'x'");
+        assert_eq!(
+            err.position(),
+            Some(Position {
+                index: 1,
+                offset: 1,
+                len: 1
+            })
+        );
+        let err = events("ab:", &["-ab"]).unwrap_err();
+        assert_eq!(err.to_string(), "option requires an argument -- 'b'");
+        assert_eq!(err.position().unwrap().offset, 2);
+        assert_eq!(
+            events("ab:", &["--all"]).unwrap_err().to_string(),
+            "invalid option '--all'"
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn bad_optstring() {
+        Getopt::new(Parser::from_args(&[] as &[&str]), "a:::");
+    }
+}
@@ -82,9 +82,31 @@ use std::{
     ffi::{OsStr, OsString},
     fmt::Display,
     mem::replace,
+    ops::Range,
+    path::PathBuf,
     str::{FromStr, Utf8Error},
 };
 
+#[cfg(feature = "serde")]
+mod de;
+mod diagnostic;
+#[cfg(feature = "docopt")]
+pub mod docopt;
+pub mod env;
+pub mod getopt;
+mod response;
+pub mod shell;
+#[cfg(feature = "spec")]
+pub mod spec;
+mod subcommand;
+pub mod windows;
+
+#[cfg(feature = "serde")]
+pub use crate::de::{from_env, from_parser};
+pub use crate::diagnostic::Diagnostic;
+pub use crate::response::ResponseFileSyntax;
+pub use crate::subcommand::Subcommands;
+
 #[cfg(unix)]
 use std::os::unix::ffi::{OsStrExt, OsStringExt};
 #[cfg(windows)]
@@ -103,17 +125,63 @@ pub struct Parser {
     state: State,
     /// The last option we emitted.
     last_option: LastOption,
+    /// Where we found the last option.
+    last_option_position: Option<Position>,
+    /// Where we found the last option or value we emitted.
+    position: Option<Position>,
+    /// The index that the argument after the last one in `source` would have.
+    end_index: usize,
+    /// A copy of the command line for diagnostics, if it was asked for with
+    /// [`Parser::set_keep_command_line`]: the index of the first argument
+    /// that was kept, and the arguments from there.
+    command_line: Option<(usize, Vec<OsString>)>,
     /// The name of the command (argv\[0\]).
     bin_name: Option<String>,
     short_equals: bool,
+    /// Whether arguments like `-5` are values instead of options.
+    negative_numbers: bool,
+    /// Short options that are digits, which take precedence over negative
+    /// numbers.
+    digit_options: Vec<char>,
+    /// Whether optional values of short options keep a leading `=`.
+    getopt_optional_values: bool,
+    /// Decides which `/name` arguments are options, if they're enabled.
+    slash_options: Option<fn(&str) -> bool>,
+    /// Which `-name` arguments are long options.
+    single_dash_long: SingleDashLong,
+    /// Whether arguments like `+x` are options.
+    plus_options: bool,
+    /// Whether arguments like `+name` are long options.
+    plus_long: bool,
+    /// Whether the option that's being parsed started with `+`.
+    plus: bool,
+    /// Known long options, for expanding abbreviations.
+    long_options: Vec<String>,
+    /// Long options that can be negated with `--no-`.
+    negatable_options: Vec<String>,
+    /// The environment variable that supplied some of the arguments, and
+    /// their indices.
+    env_args: Option<(String, Range<usize>)>,
+    /// The subcommands that were dispatched to, outermost first.
+    subcommands: Vec<String>,
+}
+
+/// Which arguments with a single dash are long options, see
+/// [`Parser::set_single_dash_long`].
+#[derive(Debug, Clone)]
+enum SingleDashLong {
+    Off,
+    All,
+    Only(Vec<String>),
 }
 
 #[derive(Debug, Clone)]
 enum State {
     /// Nothing interesting is going on.
     None,
-    /// We have a value left over from --option=value.
-    PendingValue(OsString),
+    /// We have a value left over from --option=value, with the offset and
+    /// length it has in the argument.
+    PendingValue(OsString, usize, usize),
     /// We're in the middle of -abc.
     ///
     /// On Windows and other non-UTF8-OsString platforms this Vec should
@@ -135,9 +203,39 @@ enum State {
 enum LastOption {
     None,
     Short(char),
+    Plus(char),
     Long(String),
 }
 
+/// The location of an option or value on the command line.
+///
+/// Returned by [`Parser::current_position`] and [`Parser::error_position`],
+/// and included in some errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Position {
+    /// The index of the argument in the command line.
+    ///
+    /// This counts the binary name if the parser was created with
+    /// [`Parser::from_env`] or [`Parser::from_iter`], so the first real
+    /// argument has index 1. With [`Parser::from_args`] it has index 0.
+    ///
+    /// Arguments from [`Parser::from_env_and_var`] and
+    /// [`Parser::expand_response_files`] are counted as if they had been
+    /// on the command line, so after expanding a response file the index
+    /// can be larger than the number of original arguments.
+    pub index: usize,
+    /// The byte offset inside the argument. For `q` in `-xq=foo` this is 2.
+    ///
+    /// For arguments that are not valid unicode on Windows this counts
+    /// UTF-16 code units instead.
+    pub offset: usize,
+    /// The length in bytes (or UTF-16 code units, see `offset`).
+    ///
+    /// For `--option=value` this only covers `--option`, and `value` is
+    /// reported separately.
+    pub len: usize,
+}
+
 /// A command line argument found by [`Parser`], either an option or a positional argument.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Arg<'a> {
@@ -145,10 +243,33 @@ pub enum Arg<'a> {
     Short(char),
     /// A long option, e.g. `Long("verbose")` for `--verbose`. (The dashes are not included.)
     Long(&'a str),
+    /// A short option with a plus sign, e.g. `Plus('o')` for `+o`.
+    ///
+    /// This is only returned if [`Parser::set_plus_options`] is enabled.
+    Plus(char),
+    /// A long option with plus signs, e.g. `PlusLong("nightly")` for
+    /// `++nightly`, or for `+nightly` if [`Parser::set_plus_long`] is enabled.
+    ///
+    /// This is only returned if [`Parser::set_plus_options`] is enabled.
+    PlusLong(&'a str),
     /// A positional argument, e.g. `/dev/null`.
     Value(OsString),
 }
 
+/// How a value is attached to the option that was just returned.
+///
+/// Returned by [`Parser::attached`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Attached {
+    /// The value comes after an equals sign, as in `--option=value` or
+    /// `-o=value`. The value may be empty, as in `--option=`.
+    Equals,
+    /// The value directly follows a short option, as in `-ovalue`. If the
+    /// option doesn't take a value this is more short options instead, as in
+    /// `-abc`.
+    Adjacent,
+}
+
 impl Parser {
     /// Get the next option or positional argument.
     ///
@@ -165,16 +286,18 @@ impl Parser {
     /// It's possible to continue parsing after an error (but this is rarely useful).
     pub fn next(&mut self) -> Result<Option<Arg<'_>>, Error> {
         match self.state {
-            State::PendingValue(ref mut value) => {
+            State::PendingValue(..) => {
                 // Last time we got `--long=value`, and `value` hasn't been used.
-                let value = replace(value, OsString::new());
-                self.state = State::None;
-                return Err(Error::UnexpectedValue {
-                    option: self
-                        .format_last_option()
-                        .expect("Should only have pending value after long option"),
-                    value,
-                });
+                let value = self.optional_value().unwrap();
+                return Err(self.env_context(
+                    Error::UnexpectedValue {
+                        option: self
+                            .format_last_option()
+                            .expect("Should only have pending value after long option"),
+                        value,
+                    },
+                    self.position,
+                ));
             }
             State::Shorts(ref arg, ref mut pos) => {
                 // We're somewhere inside a -abc chain. Because we're in .next(),
@@ -190,25 +313,27 @@ impl Parser {
                     // clap always interprets it as a short flag in this case, but
                     // that feels sloppy.
                     Ok(Some('=')) if *pos > 1 && self.short_equals => {
-                        return Err(Error::UnexpectedValue {
+                        let error = Error::UnexpectedValue {
                             option: self.format_last_option().unwrap(),
                             value: self.optional_value().unwrap(),
-                        });
+                        };
+                        return Err(self.env_context(error, self.position));
                     }
                     Ok(Some(ch)) => {
+                        let start = *pos;
                         *pos += ch.len_utf8();
-                        self.last_option = LastOption::Short(ch);
-                        return Ok(Some(Arg::Short(ch)));
+                        return Ok(Some(self.set_short(ch, start, ch.len_utf8())));
                     }
                     Err(err) => {
                         // Advancing may allow recovery.
                         // This is a little iffy, there might be more bad unicode next.
+                        let start = *pos;
                         match err.error_len() {
                             Some(len) => *pos += len,
                             None => *pos = arg.len(),
                         }
-                        self.last_option = LastOption::Short('�');
-                        return Ok(Some(Arg::Short('�')));
+                        let len = *pos - start;
+                        return Ok(Some(self.set_short('�', start, len)));
                     }
                 }
             }
@@ -218,24 +343,25 @@ impl Parser {
                     self.state = State::None;
                 }
                 Ok(Some('=')) if *pos > 1 && self.short_equals => {
-                    return Err(Error::UnexpectedValue {
+                    let error = Error::UnexpectedValue {
                         option: self.format_last_option().unwrap(),
                         value: self.optional_value().unwrap(),
-                    });
+                    };
+                    return Err(self.env_context(error, self.position));
                 }
                 Ok(Some(ch)) => {
+                    let start = *pos;
                     *pos += ch.len_utf16();
-                    self.last_option = LastOption::Short(ch);
-                    return Ok(Some(Arg::Short(ch)));
+                    return Ok(Some(self.set_short(ch, start, ch.len_utf16())));
                 }
                 Err(_) => {
+                    let start = *pos;
                     *pos += 1;
-                    self.last_option = LastOption::Short('�');
-                    return Ok(Some(Arg::Short('�')));
+                    return Ok(Some(self.set_short('�', start, 1)));
                 }
             },
             State::FinishedOpts => {
-                return Ok(self.source.next().map(Arg::Value));
+                return Ok(self.next_source().map(Arg::Value));
             }
             State::None => (),
         }
@@ -245,7 +371,7 @@ impl Parser {
             ref state => panic!("unexpected state {:?}", state),
         }
 
-        let arg = match self.source.next() {
+        let arg = match self.next_source() {
             Some(arg) => arg,
             None => return Ok(None),
         };
@@ -255,17 +381,46 @@ impl Parser {
             return self.next();
         }
 
+        if self.is_number(&arg) {
+            return Ok(Some(Arg::Value(arg)));
+        }
+
+        if let Some((name, name_len, value)) = self.split_slash_option(&arg) {
+            return self.set_word_option('/', name, name_len, value).map(Some);
+        }
+
+        if let Some((name, name_len, value)) = self.split_single_dash_long(&arg) {
+            return self.set_word_option('-', name, name_len, value).map(Some);
+        }
+
+        self.plus = self.is_plus_option(&arg);
+        if self.plus && self.plus_long {
+            if let Some((name, name_len, value)) = split_word_option(&arg, b'+', b"=") {
+                if !name.starts_with('+') && name.chars().nth(1).is_some() {
+                    return self.set_word_option('+', name, name_len, value).map(Some);
+                }
+            }
+        }
+        // Options that start with '+' are parsed like options that start
+        // with '-', but set_short() and set_long() return different variants.
+        let prefix = if self.plus { b'+' } else { b'-' };
+
         #[cfg(unix)]
         {
             // Fast solution for platforms where OsStrings are just UTF-8-ish bytes
             let mut arg = arg.into_vec();
-            if arg.starts_with(b"--") {
+            if arg.starts_with(&[prefix, prefix]) {
                 // Long options have two forms: --option and --option=value.
                 if let Some(ind) = arg.iter().position(|&b| b == b'=') {
                     // The value can be an OsString...
-                    self.state = State::PendingValue(OsString::from_vec(arg[ind + 1..].into()));
+                    self.state = State::PendingValue(
+                        OsString::from_vec(arg[ind + 1..].into()),
+                        ind + 1,
+                        arg.len() - ind - 1,
+                    );
                     arg.truncate(ind);
                 }
+                let len = arg.len();
                 // ...but the option has to be a string.
                 // String::from_utf8_lossy().into_owned() would work, but its
                 // return type is Cow: if the original was valid a borrowed
@@ -279,8 +434,8 @@ impl Parser {
                     Ok(text) => text,
                     Err(err) => String::from_utf8_lossy(err.as_bytes()).into_owned(),
                 };
-                Ok(Some(self.set_long(option)))
-            } else if arg.len() > 1 && arg[0] == b'-' {
+                Ok(Some(self.set_long(option, len)?))
+            } else if arg.len() > 1 && arg[0] == prefix {
                 self.state = State::Shorts(arg, 1);
                 self.next()
             } else {
@@ -296,9 +451,9 @@ impl Parser {
             {
                 // Fast path for Windows
                 let mut bytes = arg.encode_wide();
-                const DASH: u16 = b'-' as u16;
+                let dash = u16::from(prefix);
                 match (bytes.next(), bytes.next()) {
-                    (Some(DASH), Some(_)) => {
+                    (Some(first), Some(_)) if first == dash => {
                         // This is an option, we'll have to do more work.
                         // (We already checked for "--" earlier.)
                     }
@@ -324,19 +479,23 @@ impl Parser {
                         // Semantically it's UTF-16, but internally it's WTF-8 (a superset of UTF-8).
                         // So we only process the raw version here, when we know we really have to.
                         let mut arg: Vec<u16> = arg.encode_wide().collect();
-                        const DASH: u16 = b'-' as u16;
+                        let dash = u16::from(prefix);
                         const EQ: u16 = b'=' as u16;
-                        if arg.starts_with(&[DASH, DASH]) {
+                        if arg.starts_with(&[dash, dash]) {
                             if let Some(ind) = arg.iter().position(|&u| u == EQ) {
-                                self.state =
-                                    State::PendingValue(OsString::from_wide(&arg[ind + 1..]));
+                                // The positions count UTF-16 code units.
+                                self.state = State::PendingValue(
+                                    OsString::from_wide(&arg[ind + 1..]),
+                                    ind + 1,
+                                    arg.len() - ind - 1,
+                                );
                                 arg.truncate(ind);
                             }
-                            let long = self.set_long(String::from_utf16_lossy(&arg));
+                            let long = self.set_long(String::from_utf16_lossy(&arg), arg.len())?;
                             return Ok(Some(long));
                         } else {
                             assert!(arg.len() > 1);
-                            assert_eq!(arg[0], DASH);
+                            assert_eq!(arg[0], dash);
                             self.state = State::ShortsU16(arg, 1);
                             return self.next();
                         }
@@ -355,7 +514,7 @@ impl Parser {
 
                         // This allocates unconditionally, sadly.
                         let text = arg.to_string_lossy();
-                        if text.starts_with('-') {
+                        if text.as_bytes().first() == Some(&prefix) {
                             // Use the lossily patched version and hope for the best.
                             // This may be incorrect behavior.
                             // Other options are returning an error or (as of Rust 1.74)
@@ -376,13 +535,15 @@ impl Parser {
 
             // The argument is valid unicode. This is the ideal version of the
             // code, the previous mess was purely to deal with invalid unicode.
-            if arg.starts_with("--") {
+            if arg.as_bytes().starts_with(&[prefix, prefix]) {
                 if let Some(ind) = arg.find('=') {
-                    self.state = State::PendingValue(arg[ind + 1..].into());
+                    self.state =
+                        State::PendingValue(arg[ind + 1..].into(), ind + 1, arg.len() - ind - 1);
                     arg.truncate(ind);
                 }
-                Ok(Some(self.set_long(arg)))
-            } else if arg.starts_with('-') && arg != "-" {
+                let len = arg.len();
+                Ok(Some(self.set_long(arg, len)?))
+            } else if arg.as_bytes().first() == Some(&prefix) && arg.len() > 1 {
                 self.state = State::Shorts(arg.into(), 1);
                 self.next()
             } else {
@@ -405,17 +566,20 @@ impl Parser {
     /// An [`Error::MissingValue`] is returned if the end of the command
     /// line is reached.
     pub fn value(&mut self) -> Result<OsString, Error> {
-        if let Some(value) = self.optional_value() {
+        if let Some((value, _)) = self.raw_optional_value() {
             return Ok(value);
         }
 
-        if let Some(value) = self.source.next() {
+        if let Some(value) = self.next_source() {
             return Ok(value);
         }
 
-        Err(Error::MissingValue {
-            option: self.format_last_option(),
-        })
+        Err(self.env_context(
+            Error::MissingValue {
+                option: self.format_last_option(),
+            },
+            self.last_option_position,
+        ))
     }
 
     /// Gather multiple values for an option.
@@ -468,9 +632,12 @@ impl Parser {
                 parser: Some(self),
             })
         } else {
-            Err(Error::MissingValue {
-                option: self.format_last_option(),
-            })
+            Err(self.env_context(
+                Error::MissingValue {
+                    option: self.format_last_option(),
+                },
+                self.last_option_position,
+            ))
         }
     }
 
@@ -482,7 +649,7 @@ impl Parser {
     /// argument.
     fn next_if_normal(&mut self) -> Option<OsString> {
         if self.next_is_normal() {
-            self.source.next()
+            self.next_source()
         } else {
             None
         }
@@ -505,14 +672,13 @@ impl Parser {
             // "-" is the one argument with a leading '-' that's allowed.
             return true;
         }
-        #[cfg(unix)]
-        let lead_dash = arg.as_bytes().first() == Some(&b'-');
-        #[cfg(windows)]
-        let lead_dash = arg.encode_wide().next() == Some(b'-' as u16);
-        #[cfg(not(any(unix, windows)))]
-        let lead_dash = arg.to_string_lossy().as_bytes().first() == Some(&b'-');
-
-        !lead_dash
+        if self.is_number(arg) {
+            return true;
+        }
+        if self.split_slash_option(arg).is_some() || self.is_plus_option(arg) {
+            return false;
+        }
+        !starts_with_byte(arg, b'-')
     }
 
     /// Take raw arguments from the original command line.
@@ -553,10 +719,13 @@ impl Parser {
     /// ```
     pub fn raw_args(&mut self) -> Result<RawArgs<'_>, Error> {
         if let Some(value) = self.optional_value() {
-            return Err(Error::UnexpectedValue {
-                option: self.format_last_option().unwrap(),
-                value,
-            });
+            return Err(self.env_context(
+                Error::UnexpectedValue {
+                    option: self.format_last_option().unwrap(),
+                    value,
+                },
+                self.position,
+            ));
         }
 
         Ok(RawArgs(&mut self.source))
@@ -618,7 +787,7 @@ impl Parser {
     fn has_pending(&self) -> bool {
         match self.state {
             State::None | State::FinishedOpts => false,
-            State::PendingValue(_) => true,
+            State::PendingValue(..) => true,
             State::Shorts(ref arg, pos) => pos < arg.len(),
             #[cfg(windows)]
             State::ShortsU16(ref arg, pos) => pos < arg.len(),
@@ -630,6 +799,7 @@ impl Parser {
         match self.last_option {
             LastOption::None => None,
             LastOption::Short(ch) => Some(format!("-{}", ch)),
+            LastOption::Plus(ch) => Some(format!("+{}", ch)),
             LastOption::Long(ref option) => Some(option.clone()),
         }
     }
@@ -652,17 +822,242 @@ impl Parser {
         Some(self.bin_name.as_ref()?)
     }
 
+    /// The subcommands that [`Subcommands::dispatch`] has passed this parser
+    /// to, outermost first.
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(), lexopt::Error> {
+    /// # use lexopt::prelude::*;
+    /// let mut parser = lexopt::Parser::from_iter(&["cargo", "install", "ripgrep"]);
+    /// assert!(parser.subcommands().is_empty());
+    ///
+    /// let mut subcommands = lexopt::Subcommands::new();
+    /// subcommands.add("install", &[], |parser| {
+    ///     assert_eq!(parser.subcommands(), &["install"]);
+    ///     parser.next()?;
+    ///     Ok(())
+    /// });
+    /// if let Some(Value(name)) = parser.next()? {
+    ///     subcommands.dispatch(name, &mut parser)?;
+    /// }
+    /// # Ok(()) }
+    /// ```
+    pub fn subcommands(&self) -> &[String] {
+        &self.subcommands
+    }
+
+    /// The name of the command followed by the current subcommands, like
+    /// `cargo install`. This is meant to prefix messages, like
+    /// [`bin_name()`][Parser::bin_name].
+    ///
+    /// Returns `None` if there's no binary name and no subcommand.
+    pub fn command_name(&self) -> Option<String> {
+        let names: Vec<&str> = self
+            .bin_name()
+            .into_iter()
+            .chain(self.subcommands.iter().map(String::as_str))
+            .collect();
+        if names.is_empty() {
+            None
+        } else {
+            Some(names.join(" "))
+        }
+    }
+
+    /// The position of the option or value that was most recently returned
+    /// by [`next()`][Parser::next], [`value()`][Parser::value],
+    /// [`values()`][Parser::values] or [`optional_value()`][Parser::optional_value].
+    ///
+    /// Arguments taken with [`raw_args()`][Parser::raw_args] are not tracked.
+    ///
+    /// This can be used to point out an argument in an error message. See
+    /// also [`Parser::error_position`].
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(), lexopt::Error> {
+    /// # use lexopt::prelude::*;
+    /// use lexopt::Position;
+    ///
+    /// let mut parser = lexopt::Parser::from_iter(&["myapp", "-x", "-xq=foo"]);
+    /// parser.next()?;
+    /// parser.next()?;
+    /// assert_eq!(parser.next()?, Some(Short('q')));
+    /// assert_eq!(
+    ///     parser.current_position(),
+    ///     Some(Position { index: 2, offset: 2, len: 1 }),
+    /// );
+    /// assert_eq!(parser.value()?, "foo");
+    /// assert_eq!(
+    ///     parser.current_position(),
+    ///     Some(Position { index: 2, offset: 4, len: 3 }),
+    /// );
+    /// # Ok(()) }
+    /// ```
+    pub fn current_position(&self) -> Option<Position> {
+        self.position
+    }
+
+    /// The position of the argument that an error is about, if known.
+    ///
+    /// If the error has a position of its own (see [`Error::position`]) then
+    /// that's returned. [`Error::MissingValue`], [`Error::UnexpectedValue`],
+    /// [`Error::UnexpectedOption`] and [`Error::UnexpectedArgument`] don't,
+    /// so for them the position is taken from the parser's state. That's
+    /// only right if the error was just returned by the parser, or was made
+    /// from the argument it just returned with [`Arg::unexpected`].
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(), lexopt::Error> {
+    /// # use lexopt::prelude::*;
+    /// use lexopt::Position;
+    ///
+    /// let mut parser = lexopt::Parser::from_iter(&["myapp", "-x", "-xq"]);
+    /// let mut errors = Vec::new();
+    /// while let Some(arg) = parser.next()? {
+    ///     match arg {
+    ///         Short('x') => (),
+    ///         _ => {
+    ///             let error = arg.unexpected();
+    ///             errors.push((parser.error_position(&error), error));
+    ///         }
+    ///     }
+    /// }
+    /// assert_eq!(errors[0].0, Some(Position { index: 2, offset: 2, len: 1 }));
+    /// # Ok(()) }
+    /// ```
+    pub fn error_position(&self, error: &Error) -> Option<Position> {
+        if let Some(position) = error.position() {
+            return Some(position);
+        }
+        match *error {
+            Error::MissingValue { .. } => self.last_option_position,
+            Error::UnexpectedOption(_)
+            | Error::UnexpectedArgument(_)
+            | Error::UnexpectedValue { .. } => self.position,
+            // Errors from env::Fallback are about a variable, not about an
+            // argument.
+            Error::Env { ref var, ref error } => self
+                .error_position(error)
+                .filter(|&position| self.env_var_at(position) == Some(var.as_str())),
+            _ => None,
+        }
+    }
+
     /// Get a value only if it's concatenated to an option, as in `-ovalue` or
     /// `--option=value` or `-o=value`, but not `-o value` or `--option value`.
+    ///
+    /// An empty value (`--option=`) is returned as an empty string, so it
+    /// can be told apart from `--option`. See also [`Parser::attached`].
     pub fn optional_value(&mut self) -> Option<OsString> {
+        if self.getopt_optional_values {
+            let short_equals = replace(&mut self.short_equals, false);
+            let value = self.raw_optional_value();
+            self.short_equals = short_equals;
+            return Some(value?.0);
+        }
         Some(self.raw_optional_value()?.0)
     }
 
+    /// Check whether a value is attached to the option that was just
+    /// returned, without consuming it.
+    ///
+    /// This returns `None` for `--option` and `-o`, [`Attached::Equals`] for
+    /// `--option=value`, `--option=` and `-o=value`, and
+    /// [`Attached::Adjacent`] for `-ovalue`. If
+    /// [`short_equals`](Parser::set_short_equals) is disabled then `-o=value`
+    /// counts as adjacent.
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(), lexopt::Error> {
+    /// # use lexopt::prelude::*;
+    /// use lexopt::Attached;
+    ///
+    /// let mut parser = lexopt::Parser::from_args(&["--color", "--color=", "-cv"]);
+    /// assert_eq!(parser.next()?, Some(Long("color")));
+    /// assert_eq!(parser.attached(), None);
+    /// assert_eq!(parser.next()?, Some(Long("color")));
+    /// assert_eq!(parser.attached(), Some(Attached::Equals));
+    /// assert_eq!(parser.optional_value().unwrap(), "");
+    /// assert_eq!(parser.next()?, Some(Short('c')));
+    /// assert_eq!(parser.attached(), Some(Attached::Adjacent));
+    /// # Ok(()) }
+    /// ```
+    pub fn attached(&self) -> Option<Attached> {
+        match self.state {
+            State::PendingValue(..) => Some(Attached::Equals),
+            State::Shorts(ref arg, pos) if pos < arg.len() => {
+                if arg[pos] == b'=' && self.short_equals {
+                    Some(Attached::Equals)
+                } else {
+                    Some(Attached::Adjacent)
+                }
+            }
+            #[cfg(windows)]
+            State::ShortsU16(ref arg, pos) if pos < arg.len() => {
+                if arg[pos] == b'=' as u16 && self.short_equals {
+                    Some(Attached::Equals)
+                } else {
+                    Some(Attached::Adjacent)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Check whether the last option was the negated form of a negatable
+    /// option, like `--no-color` for `color`.
+    ///
+    /// See [`set_negatable_options`](Parser::set_negatable_options).
+    pub fn negated(&self) -> bool {
+        match self.last_option {
+            LastOption::Long(ref option) => self.is_negation(option),
+            _ => false,
+        }
+    }
+
+    /// Report an error if the option that was just returned has a value
+    /// attached with an equals sign, as in `--flag=value` or `-f=value`.
+    ///
+    /// [`next()`](Parser::next) already does this, but it's easy to miss if
+    /// you stop parsing after an option. In `-abc` the `b` and `c` are
+    /// options, so that's not an error.
+    ///
+    /// # Example
+    /// ```
+    /// # use lexopt::prelude::*;
+    /// let mut parser = lexopt::Parser::from_args(&["--help=yes"]);
+    /// assert_eq!(parser.next().unwrap(), Some(Long("help")));
+    /// assert_eq!(
+    ///     parser.reject_value().unwrap_err().to_string(),
+    ///     r#"unexpected argument for option '--help': "yes""#,
+    /// );
+    /// ```
+    pub fn reject_value(&mut self) -> Result<(), Error> {
+        if self.attached() != Some(Attached::Equals) {
+            return Ok(());
+        }
+        let (value, _) = self.raw_optional_value().unwrap();
+        Err(self.env_context(
+            Error::UnexpectedValue {
+                option: self.format_last_option().unwrap(),
+                value,
+            },
+            self.position,
+        ))
+    }
+
     /// [`Parser::optional_value`], but indicate whether the value was joined
     /// with an = sign. This matters for [`Parser::values`].
     fn raw_optional_value(&mut self) -> Option<(OsString, bool)> {
         match replace(&mut self.state, State::None) {
-            State::PendingValue(value) => Some((value, true)),
+            State::PendingValue(value, offset, len) => {
+                self.set_position(offset, len);
+                Some((value, true))
+            }
             State::Shorts(mut arg, mut pos) => {
                 if pos >= arg.len() {
                     return None;
@@ -675,6 +1070,7 @@ impl Parser {
                     pos += 1;
                     had_eq_sign = true;
                 }
+                self.set_position(pos, arg.len() - pos);
                 arg.drain(..pos); // Reuse allocation
                 #[cfg(unix)]
                 {
@@ -697,6 +1093,7 @@ impl Parser {
                     pos += 1;
                     had_eq_sign = true;
                 }
+                self.set_position(pos, arg.len() - pos);
                 Some((OsString::from_wide(&arg[pos..]), had_eq_sign))
             }
             State::FinishedOpts => {
@@ -710,14 +1107,30 @@ impl Parser {
 
     fn new(bin_name: Option<OsString>, source: InnerIter) -> Parser {
         Parser {
+            end_index: bin_name.is_some() as usize + source.len(),
+            command_line: None,
             source,
             state: State::None,
             last_option: LastOption::None,
+            last_option_position: None,
+            position: None,
             bin_name: bin_name.map(|s| match s.into_string() {
                 Ok(text) => text,
                 Err(text) => text.to_string_lossy().into_owned(),
             }),
             short_equals: true,
+            negative_numbers: false,
+            digit_options: Vec::new(),
+            getopt_optional_values: false,
+            slash_options: None,
+            single_dash_long: SingleDashLong::Off,
+            plus_options: false,
+            plus_long: false,
+            plus: false,
+            long_options: Vec::new(),
+            negatable_options: Vec::new(),
+            env_args: None,
+            subcommands: Vec::new(),
         }
     }
 
@@ -729,6 +1142,111 @@ impl Parser {
         Parser::new(source.next(), source)
     }
 
+    /// Create a parser from the environment, with extra arguments taken from
+    /// the environment variable `var`.
+    ///
+    /// The variable is split into arguments by [`shell::split`], like a POSIX
+    /// shell would but without any expansions. The arguments are inserted right after the
+    /// binary name, so options on the command line take precedence if the
+    /// program lets later options override earlier ones.
+    ///
+    /// Errors that the parser returns about these arguments are wrapped in
+    /// [`Error::Env`]. Errors you create yourself, like those from
+    /// [`Arg::unexpected`] and [`ValueExt::parse`], are not. To find out
+    /// whether an argument came from the variable, use
+    /// [`env_var_at()`](Parser::env_var_at).
+    ///
+    /// # Errors
+    ///
+    /// [`Error::Env`] is returned if the variable contains an unterminated
+    /// quote.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # fn main() -> Result<(), lexopt::Error> {
+    /// // Like less(1), which reads default options from $LESS
+    /// let mut parser = lexopt::Parser::from_env_and_var("LESS")?;
+    /// # Ok(()) }
+    /// ```
+    pub fn from_env_and_var(var: &str) -> Result<Parser, Error> {
+        let mut parser = Parser::from_env();
+        if let Some(value) = std::env::var_os(var) {
+            parser.prepend_args(var, &value)?;
+        }
+        Ok(parser)
+    }
+
+    /// Insert arguments from an environment variable before the others.
+    fn prepend_args(&mut self, var: &str, value: &OsStr) -> Result<(), Error> {
+        let mut args = shell::split(value).map_err(|err| Error::Env {
+            var: var.to_owned(),
+            error: Box::new(err.into()),
+        })?;
+        let start = self.end_index - self.source.len();
+        let count = args.len();
+        args.extend(self.source.by_ref());
+        if let Some((first, ref mut command_line)) = self.command_line {
+            command_line.splice(start - first..start - first, args[..count].iter().cloned());
+        }
+        self.end_index += count;
+        self.source = args.into_iter();
+        self.env_args = Some((var.to_owned(), start..start + count));
+        Ok(())
+    }
+
+    /// Return the name of the environment variable that the argument at
+    /// `position` came from, if it came from one.
+    ///
+    /// This can be used to add context to errors like those from
+    /// [`Arg::unexpected`] and [`ValueExt::parse`]. Errors created by the
+    /// parser itself already have this context.
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(), lexopt::Error> {
+    /// # use lexopt::prelude::*;
+    /// # let mut parser = lexopt::Parser::from_env();
+    /// while let Some(arg) = parser.next()? {
+    ///     match arg {
+    ///         // ...
+    ///         _ => {
+    ///             let error = arg.unexpected();
+    ///             let position = parser.error_position(&error);
+    ///             return Err(match position.and_then(|pos| parser.env_var_at(pos)) {
+    ///                 Some(var) => lexopt::Error::Env {
+    ///                     var: var.to_owned(),
+    ///                     error: Box::new(error),
+    ///                 },
+    ///                 None => error,
+    ///             });
+    ///         }
+    ///     }
+    /// }
+    /// # Ok(()) }
+    /// ```
+    pub fn env_var_at(&self, position: Position) -> Option<&str> {
+        match self.env_args {
+            Some((ref var, ref range))
+                if range.start <= position.index && position.index < range.end =>
+            {
+                Some(var)
+            }
+            _ => None,
+        }
+    }
+
+    /// Wrap an error in [`Error::Env`] if it's about an argument from an
+    /// environment variable, at `position`.
+    fn env_context(&self, error: Error, position: Option<Position>) -> Error {
+        match position.and_then(|position| self.env_var_at(position)) {
+            Some(var) => Error::Env {
+                var: var.to_owned(),
+                error: Box::new(error),
+            },
+            None => error,
+        }
+    }
+
     // The collision with `FromIterator::from_iter` is a bit unfortunate.
     // This name is used because:
     // - `from_args()` was taken, and changing its behavior without changing
@@ -772,15 +1290,353 @@ impl Parser {
         Parser::new(None, make_iter(args.into_iter().map(Into::into)))
     }
 
-    /// Store a long option so the caller can borrow it.
-    fn set_long(&mut self, option: String) -> Arg<'_> {
+    /// Expand response files in the remaining arguments.
+    ///
+    /// An argument of the form `@path` is replaced by the arguments contained
+    /// in the file at `path`, split according to `syntax`. Response files may
+    /// refer to other response files, up to `max_depth` levels deep. Relative
+    /// paths are resolved against the current directory.
+    ///
+    /// Arguments after `--` are not expanded, and neither is a lone `@`. Note
+    /// that this happens before parsing, so an option's value (like `-o @out`)
+    /// is expanded as well.
+    ///
+    /// This has to be called right after creating the parser, before any
+    /// arguments are taken. The binary name is never expanded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if arguments have already been taken, by [`next()`](Parser::next)
+    /// or any other method.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::ResponseFile`] is returned if a file can't be read, if it
+    /// contains an unterminated quote, if response files are nested more than
+    /// `max_depth` levels deep, or if a response file includes itself. In that
+    /// case the parser is left unchanged.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # fn main() -> Result<(), lexopt::Error> {
+    /// use lexopt::ResponseFileSyntax;
+    ///
+    /// let mut parser = lexopt::Parser::from_env();
+    /// parser.expand_response_files(ResponseFileSyntax::Gnu, 10)?;
+    /// # Ok(()) }
+    /// ```
+    pub fn expand_response_files(
+        &mut self,
+        syntax: ResponseFileSyntax,
+        max_depth: usize,
+    ) -> Result<(), Error> {
+        assert!(
+            self.end_index - self.source.len() == self.bin_name.is_some() as usize,
+            "expand_response_files() must be called before parsing"
+        );
+        let expanded = response::expand(self.source.as_slice(), syntax, max_depth)?;
+        let start = self.end_index - self.source.len();
+        self.end_index = start + expanded.len();
+        if let Some((first, ref mut command_line)) = self.command_line {
+            command_line.truncate(start - first);
+            command_line.extend(expanded.iter().cloned());
+        }
+        self.source = expanded.into_iter();
+        Ok(())
+    }
+
+    /// Take the next whole argument from `source` and remember where it was.
+    fn next_source(&mut self) -> Option<OsString> {
+        let arg = self.source.next()?;
+        self.set_position(0, arg.len());
+        Some(arg)
+    }
+
+    /// The index of the argument we most recently took from `source`.
+    fn current_index(&self) -> usize {
+        self.end_index - self.source.len() - 1
+    }
+
+    /// Record the position of something inside the current argument.
+    fn set_position(&mut self, offset: usize, len: usize) {
+        self.position = Some(Position {
+            index: self.current_index(),
+            offset,
+            len,
+        });
+    }
+
+    /// Store a short option and its position.
+    fn set_short(&mut self, option: char, offset: usize, len: usize) -> Arg<'static> {
+        self.set_position(offset, len);
+        self.last_option_position = self.position;
+        if self.plus {
+            self.last_option = LastOption::Plus(option);
+            Arg::Plus(option)
+        } else {
+            self.last_option = LastOption::Short(option);
+            Arg::Short(option)
+        }
+    }
+
+    /// Store a long option so the caller can borrow it. `len` is its length in
+    /// the original argument.
+    ///
+    /// If the option is an abbreviation of a known long option it's expanded first.
+    fn set_long(&mut self, option: String, len: usize) -> Result<Arg<'_>, Error> {
+        self.set_position(0, len);
+        if self.plus {
+            self.last_option = LastOption::Long(option);
+            self.last_option_position = self.position;
+            return match self.last_option {
+                LastOption::Long(ref option) => Ok(Arg::PlusLong(&option[2..])),
+                _ => unreachable!(),
+            };
+        }
+        let option = match self.expand_long(option, 2) {
+            Ok(option) => option,
+            Err(err) => {
+                // Any value belongs to the ambiguous option, so discard it.
+                self.state = State::None;
+                return Err(self.env_context(err.with_position(self.position), self.position));
+            }
+        };
+        let negated = self.is_negation(&option);
         self.last_option = LastOption::Long(option);
+        self.last_option_position = self.position;
         match self.last_option {
-            LastOption::Long(ref option) => Arg::Long(&option[2..]),
+            LastOption::Long(ref option) if negated => Ok(Arg::Long(&option[5..])),
+            LastOption::Long(ref option) => Ok(Arg::Long(&option[2..])),
             _ => unreachable!(),
         }
     }
 
+    /// Whether a long option (including the dashes) is `--no-` followed by
+    /// a negatable option.
+    fn is_negation(&self, option: &str) -> bool {
+        option.starts_with("--no-")
+            && self
+                .negatable_options
+                .iter()
+                .any(|known| *known == option[5..])
+    }
+
+    /// Store an option that was written as a single word after a one-byte
+    /// prefix, like `/out`, `-name` or `+name`, with its value if it has one.
+    ///
+    /// Names of more than one character are expanded like in `set_long()`,
+    /// except after `+`.
+    fn set_word_option(
+        &mut self,
+        prefix: char,
+        name: String,
+        name_len: usize,
+        value: Option<OsString>,
+    ) -> Result<Arg<'_>, Error> {
+        self.set_position(0, name_len);
+        let mut option = format!("{}{}", prefix, name);
+        if prefix != '+' && name.chars().nth(1).is_some() {
+            option = match self.expand_long(option, 1) {
+                Ok(option) => option,
+                Err(err) => {
+                    return Err(self.env_context(err.with_position(self.position), self.position));
+                }
+            };
+        }
+        if let Some(value) = value {
+            // The name is valid unicode, so it's up to the value whether
+            // positions count bytes or UTF-16 code units.
+            #[cfg(windows)]
+            let len = match value.to_str() {
+                Some(text) => text.len(),
+                None => value.encode_wide().count(),
+            };
+            #[cfg(not(windows))]
+            let len = value.len();
+            self.state = State::PendingValue(value, name_len + 1, len);
+        }
+        let mut chars = name.chars();
+        let short = match (chars.next(), chars.next()) {
+            (Some(ch), None) => Some(ch),
+            _ => None,
+        };
+        self.last_option = LastOption::Long(option);
+        self.last_option_position = self.position;
+        Ok(match (short, &self.last_option) {
+            (Some(ch), _) => Arg::Short(ch),
+            (None, LastOption::Long(option)) if prefix == '+' => Arg::PlusLong(&option[1..]),
+            (None, LastOption::Long(option)) => Arg::Long(&option[1..]),
+            _ => unreachable!(),
+        })
+    }
+
+    /// Whether an argument starts with `+` and should be parsed as an option.
+    fn is_plus_option(&self, arg: &OsStr) -> bool {
+        self.plus_options && starts_with_byte(arg, b'+') && arg != "+" && arg != "++"
+    }
+
+    /// Split an argument like `/name:value` into the name, the length of
+    /// `/name` and the value, if slash options are enabled and the filter
+    /// accepts the name.
+    fn split_slash_option(&self, arg: &OsStr) -> Option<(String, usize, Option<OsString>)> {
+        let filter = self.slash_options?;
+        let (name, name_len, value) = split_word_option(arg, b'/', b":=")?;
+        if filter(&name) {
+            Some((name, name_len, value))
+        } else {
+            None
+        }
+    }
+
+    /// Split an argument like `-name=value` in the same way, if it should be
+    /// a single-dash long option.
+    fn split_single_dash_long(&self, arg: &OsStr) -> Option<(String, usize, Option<OsString>)> {
+        if let SingleDashLong::Off = self.single_dash_long {
+            return None;
+        }
+        let (name, name_len, value) = split_word_option(arg, b'-', b"=")?;
+        if name.starts_with('-') || name.chars().nth(1).is_none() {
+            return None;
+        }
+        match self.single_dash_long {
+            SingleDashLong::Only(ref known)
+                if !known.contains(&name) && !self.abbreviates(known, &name) =>
+            {
+                None
+            }
+            _ => Some((name, name_len, value)),
+        }
+    }
+
+    /// Whether `name` could be an abbreviation of one of `options` that's
+    /// also a known long option.
+    fn abbreviates(&self, options: &[String], name: &str) -> bool {
+        options
+            .iter()
+            .any(|option| option.starts_with(name) && self.long_options.contains(option))
+    }
+
+    /// Expand an abbreviated long option if it's the prefix of exactly one
+    /// known long option. `option` includes the prefix, which is `prefix_len`
+    /// bytes long and is kept.
+    fn expand_long(&self, option: String, prefix_len: usize) -> Result<String, Error> {
+        let (prefix, name) = option.split_at(prefix_len);
+        if name.is_empty() || self.long_options.iter().any(|known| known == name) {
+            return Ok(option);
+        }
+        let candidates: Vec<&String> = self
+            .long_options
+            .iter()
+            .filter(|known| known.starts_with(name))
+            .collect();
+        match candidates.len() {
+            0 => Ok(option),
+            1 => Ok(format!("{}{}", prefix, candidates[0])),
+            _ => Err(Error::AmbiguousOption {
+                candidates: candidates
+                    .into_iter()
+                    .map(|known| format!("{}{}", prefix, known))
+                    .collect(),
+                option,
+                position: None,
+            }),
+        }
+    }
+
+    /// Register the known long options, to allow abbreviating them.
+    ///
+    /// If a long option on the command line is not one of these names but is
+    /// a prefix of exactly one of them then [`next()`][Parser::next] returns
+    /// the full name instead. For example, if `verbose` and `version` are
+    /// registered then `--verb` is returned as `Long("verbose")`, while
+    /// `--ver` is an error. This is how GNU's `getopt_long` behaves.
+    ///
+    /// The names should not include the leading dashes. Options that aren't
+    /// a prefix of any of these names are returned as-is, so they still
+    /// reach [`Arg::unexpected`]. An empty slice turns abbreviations off again.
+    ///
+    /// Options like `-name` and `/name` from
+    /// [`set_single_dash_long()`](Parser::set_single_dash_long) and
+    /// [`set_slash_options()`](Parser::set_slash_options) are expanded in
+    /// the same way, but `-n` and `/n` stay short options because only you
+    /// know whether a short option with that name exists. With
+    /// [`set_single_dash_long_options()`](Parser::set_single_dash_long_options)
+    /// an abbreviation of one of the listed options is a long option too.
+    ///
+    /// Abbreviations can be confusing and make it harder to add options later
+    /// without breaking scripts, so consider carefully whether you want this.
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(), lexopt::Error> {
+    /// # use lexopt::prelude::*;
+    /// let mut parser = lexopt::Parser::from_args(&["--verb", "--col=never", "--ver"]);
+    /// parser.set_long_options(&["verbose", "version", "color"]);
+    /// assert_eq!(parser.next()?, Some(Long("verbose")));
+    /// assert_eq!(parser.next()?, Some(Long("color")));
+    /// assert_eq!(parser.value()?, "never");
+    /// assert_eq!(
+    ///     parser.next().unwrap_err().to_string(),
+    ///     "option '--ver' is ambiguous; possibilities: '--verbose' '--version'",
+    /// );
+    /// # Ok(()) }
+    /// ```
+    pub fn set_long_options<I>(&mut self, options: I)
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.long_options.clear();
+        for option in options {
+            let option = option.as_ref();
+            if !self.long_options.iter().any(|known| known == option) {
+                self.long_options.push(option.to_owned());
+            }
+        }
+    }
+
+    /// Register the long options that can be negated with `--no-`.
+    ///
+    /// If `color` is registered then `--no-color` is returned as
+    /// `Long("color")`, and [`negated()`](Parser::negated) returns `true`
+    /// until the next option. This makes it easy to let the last occurrence
+    /// win, as in `--color --no-color`.
+    ///
+    /// The names should not include the dashes or the `no-`. Other options
+    /// that start with `no-` are returned as-is, so `--no-xyz` still reaches
+    /// [`Arg::unexpected`] as `--no-xyz`. Errors about a negated option, like
+    /// an unexpected value in `--no-color=x`, show it the way it was written.
+    /// An empty slice turns negation off again.
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(), lexopt::Error> {
+    /// # use lexopt::prelude::*;
+    /// let mut parser = lexopt::Parser::from_args(&["--color", "--no-color", "--no-pager"]);
+    /// parser.set_negatable_options(&["color"]);
+    /// let mut color = false;
+    /// while let Some(arg) = parser.next()? {
+    ///     match arg {
+    ///         Long("color") => color = !parser.negated(),
+    ///         _ => {
+    ///             assert_eq!(arg.unexpected().to_string(), "invalid option '--no-pager'");
+    ///         }
+    ///     }
+    /// }
+    /// assert!(!color);
+    /// # Ok(()) }
+    /// ```
+    pub fn set_negatable_options<I>(&mut self, options: I)
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.negatable_options = options
+            .into_iter()
+            .map(|option| option.as_ref().to_owned())
+            .collect();
+    }
+
     /// Configure whether to parse an equals sign (`=`) for short options.
     ///
     /// If this is **true** (the default), `-o=foobar` will be interpreted as
@@ -828,6 +1684,305 @@ impl Parser {
     pub fn set_short_equals(&mut self, on: bool) {
         self.short_equals = on;
     }
+
+    /// Treat arguments that look like negative numbers as values instead of
+    /// options. This is off by default.
+    ///
+    /// With this enabled [`next()`](Parser::next) returns `-5` as
+    /// `Value("-5")` instead of `Short('5')`, and [`values()`](Parser::values)
+    /// doesn't stop at it. (`value()` always takes the next argument, even
+    /// without this setting.)
+    ///
+    /// An argument counts as a negative number if it's a `-` followed by an
+    /// integer or a decimal number, with an optional exponent: `-5`, `-0.5`,
+    /// `-.5`, `-5.`, `-1e10` and `-1.5E-3` are all numbers. So are `-inf`,
+    /// `-infinity` and `-nan`, in any case.
+    ///
+    /// If your program has short options that are digits, like `-1`, then
+    /// register them with [`set_digit_options()`](Parser::set_digit_options)
+    /// so they can still be used.
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(), lexopt::Error> {
+    /// # use lexopt::prelude::*;
+    /// let mut parser = lexopt::Parser::from_args(&["--range", "-3", "7", "-x", "-1.5e3"]);
+    /// parser.set_negative_numbers(true);
+    /// assert_eq!(parser.next()?, Some(Long("range")));
+    /// let range: Vec<i32> = parser.values()?.map(|v| v.parse()).collect::<Result<_, _>>()?;
+    /// assert_eq!(range, &[-3, 7]);
+    /// assert_eq!(parser.next()?, Some(Short('x')));
+    /// assert_eq!(parser.next()?, Some(Value("-1.5e3".into())));
+    /// # Ok(()) }
+    /// ```
+    pub fn set_negative_numbers(&mut self, on: bool) {
+        self.negative_numbers = on;
+    }
+
+    /// Declare short options that are digits, like `-1` in `gzip -1`, so
+    /// that [`set_negative_numbers()`](Parser::set_negative_numbers) doesn't
+    /// treat them as numbers.
+    ///
+    /// An argument that starts with `-` and one of these digits is always
+    /// parsed as short options, so `-1` and `-15` are options but `-25` is a
+    /// number if only `1` is registered.
+    ///
+    /// Calling this again replaces the list.
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(), lexopt::Error> {
+    /// # use lexopt::prelude::*;
+    /// let mut parser = lexopt::Parser::from_args(&["-1", "--offset", "-5"]);
+    /// parser.set_negative_numbers(true);
+    /// parser.set_digit_options("123".chars());
+    /// assert_eq!(parser.next()?, Some(Short('1')));
+    /// assert_eq!(parser.next()?, Some(Long("offset")));
+    /// assert_eq!(parser.values()?.collect::<Vec<_>>(), &["-5"]);
+    /// # Ok(()) }
+    /// ```
+    pub fn set_digit_options<I>(&mut self, options: I)
+    where
+        I: IntoIterator<Item = char>,
+    {
+        self.digit_options = options.into_iter().collect();
+    }
+
+    /// Whether an argument should be a value because it's a negative number.
+    fn is_number(&self, arg: &OsStr) -> bool {
+        if !self.negative_numbers || !is_negative_number(arg) {
+            return false;
+        }
+        match arg.to_str().and_then(|text| text[1..].chars().next()) {
+            Some(first) => !self.digit_options.contains(&first),
+            None => true,
+        }
+    }
+
+    /// Make [`optional_value()`](Parser::optional_value) behave like an
+    /// optional argument (`c::`) in POSIX `getopt`: for short options it
+    /// returns everything after the option, including a leading `=`. This is
+    /// off by default.
+    ///
+    /// [`value()`](Parser::value) and long options are not affected, so
+    /// `-o=value` and `--color=value` still lose their `=`. To make `value()`
+    /// keep the `=` as well, like a required argument (`c:`) in `getopt`,
+    /// also call [`set_short_equals(false)`](Parser::set_short_equals). That
+    /// alone already covers `optional_value()`, so this setting is only
+    /// needed if `value()` should keep stripping the `=`.
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(), lexopt::Error> {
+    /// # use lexopt::prelude::*;
+    /// let mut parser = lexopt::Parser::from_args(&["-c=x", "-c", "x", "--color=x"]);
+    /// parser.set_getopt_optional_values(true);
+    /// assert_eq!(parser.next()?, Some(Short('c')));
+    /// assert_eq!(parser.optional_value().unwrap(), "=x");
+    /// assert_eq!(parser.next()?, Some(Short('c')));
+    /// assert_eq!(parser.optional_value(), None);
+    /// assert_eq!(parser.next()?, Some(Value("x".into())));
+    /// assert_eq!(parser.next()?, Some(Long("color")));
+    /// assert_eq!(parser.optional_value().unwrap(), "x");
+    ///
+    /// let mut parser = lexopt::Parser::from_args(&["-b=x"]);
+    /// parser.set_getopt_optional_values(true);
+    /// assert_eq!(parser.next()?, Some(Short('b')));
+    /// assert_eq!(parser.value()?, "x");
+    /// # Ok(()) }
+    /// ```
+    pub fn set_getopt_optional_values(&mut self, on: bool) {
+        self.getopt_optional_values = on;
+    }
+
+    /// Accept options that start with a slash, like MSVC's `/help`, `/v`
+    /// and `/out:file`. This is off by default, and works the same way on
+    /// all platforms.
+    ///
+    /// A slash option with a single character is returned as
+    /// [`Short`][Arg::Short], and a longer one as [`Long`][Arg::Long], so
+    /// `/v` matches `Short('v')` and `/help` matches `Long("help")`. A value
+    /// can be attached with `:` or `=`, and is taken by
+    /// [`value()`](Parser::value) like the value in `--out=file`. Options with
+    /// dashes keep working.
+    ///
+    /// An argument is only treated as an option if it passes
+    /// [`slash_option_heuristic`], which rejects names that contain another
+    /// slash, like `/usr/bin`. Use [`set_slash_option_filter`](Parser::set_slash_option_filter)
+    /// to decide this yourself.
+    ///
+    /// Error messages show the option the way it was written, like `/out`,
+    /// except for errors created by [`Arg::unexpected`], which only knows the
+    /// name and uses dashes.
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(), lexopt::Error> {
+    /// # use lexopt::prelude::*;
+    /// let mut parser = lexopt::Parser::from_args(&["/v", "/out:a.exe", "/usr/bin", "-x"]);
+    /// parser.set_slash_options(true);
+    /// assert_eq!(parser.next()?, Some(Short('v')));
+    /// assert_eq!(parser.next()?, Some(Long("out")));
+    /// assert_eq!(parser.value()?, "a.exe");
+    /// assert_eq!(parser.next()?, Some(Value("/usr/bin".into())));
+    /// assert_eq!(parser.next()?, Some(Short('x')));
+    /// # Ok(()) }
+    /// ```
+    pub fn set_slash_options(&mut self, on: bool) {
+        self.slash_options = if on {
+            Some(slash_option_heuristic)
+        } else {
+            None
+        };
+    }
+
+    /// Accept options that start with a slash, like
+    /// [`set_slash_options`](Parser::set_slash_options), but let `filter`
+    /// decide which arguments are options.
+    ///
+    /// `filter` receives the name of the option, without the slash and the
+    /// value. If it returns `false` the argument is a positional argument.
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(), lexopt::Error> {
+    /// # use lexopt::prelude::*;
+    /// let mut parser = lexopt::Parser::from_args(&["/help", "/tmp"]);
+    /// parser.set_slash_option_filter(|name| ["help", "out", "v"].contains(&name));
+    /// assert_eq!(parser.next()?, Some(Long("help")));
+    /// assert_eq!(parser.next()?, Some(Value("/tmp".into())));
+    /// # Ok(()) }
+    /// ```
+    pub fn set_slash_option_filter(&mut self, filter: fn(&str) -> bool) {
+        self.slash_options = Some(filter);
+    }
+
+    /// Treat arguments like `-name` as long options, like `find`, `java` and
+    /// X11 programs do. This is off by default.
+    ///
+    /// An argument with a single dash and a name of more than one character
+    /// is returned as [`Long`][Arg::Long], so `-name` matches `Long("name")`.
+    /// A value can be attached with `=`, as in `-name=value`. Short options
+    /// like `-v` still work, but can't be combined: `-vx` is `Long("vx")`.
+    /// Options with two dashes keep working as well.
+    ///
+    /// To keep combining short options, list the long options with
+    /// [`set_single_dash_long_options`](Parser::set_single_dash_long_options)
+    /// instead.
+    ///
+    /// Error messages show the option with a single dash, like `-name`,
+    /// except for errors created by [`Arg::unexpected`], which only knows the
+    /// name and uses two dashes.
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(), lexopt::Error> {
+    /// # use lexopt::prelude::*;
+    /// let mut parser = lexopt::Parser::from_args(&["-name", "*.rs", "-print", "-v"]);
+    /// parser.set_single_dash_long(true);
+    /// assert_eq!(parser.next()?, Some(Long("name")));
+    /// assert_eq!(parser.value()?, "*.rs");
+    /// assert_eq!(parser.next()?, Some(Long("print")));
+    /// assert_eq!(parser.next()?, Some(Short('v')));
+    /// # Ok(()) }
+    /// ```
+    pub fn set_single_dash_long(&mut self, on: bool) {
+        self.single_dash_long = if on {
+            SingleDashLong::All
+        } else {
+            SingleDashLong::Off
+        };
+    }
+
+    /// Treat arguments like `-name` as long options if the name is one of
+    /// `options`, and split them into short options otherwise.
+    ///
+    /// This is like [`set_single_dash_long`](Parser::set_single_dash_long),
+    /// except that `-vx` is still `Short('v')` followed by `Short('x')` if
+    /// `vx` isn't in the list. The names should not include the dash. An empty
+    /// list turns single-dash long options off again.
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(), lexopt::Error> {
+    /// # use lexopt::prelude::*;
+    /// let mut parser = lexopt::Parser::from_args(&["-version", "-vx"]);
+    /// parser.set_single_dash_long_options(&["version", "help"]);
+    /// assert_eq!(parser.next()?, Some(Long("version")));
+    /// assert_eq!(parser.next()?, Some(Short('v')));
+    /// assert_eq!(parser.next()?, Some(Short('x')));
+    /// # Ok(()) }
+    /// ```
+    pub fn set_single_dash_long_options<I>(&mut self, options: I)
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let options: Vec<String> = options
+            .into_iter()
+            .map(|option| option.as_ref().to_owned())
+            .collect();
+        self.single_dash_long = if options.is_empty() {
+            SingleDashLong::Off
+        } else {
+            SingleDashLong::Only(options)
+        };
+    }
+
+    /// Accept options that start with a plus sign, like `+x`, as used by
+    /// `set +e` and `xterm +sb`. This is off by default.
+    ///
+    /// These are parsed the same way as options with a dash, but returned as
+    /// [`Plus`][Arg::Plus] and [`PlusLong`][Arg::PlusLong]: `+abc` is
+    /// `Plus('a')`, `Plus('b')`, `Plus('c')`, and `++name=value` is
+    /// `PlusLong("name")` with a value. Values work like they do for short
+    /// and long options, so `+ofoo`, `+o foo` and `+o=foo` all give `+o` the
+    /// value `foo`. A lone `+` or `++` is a positional argument.
+    ///
+    /// Plus options are never expanded by
+    /// [`set_long_options`](Parser::set_long_options).
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(), lexopt::Error> {
+    /// # use lexopt::prelude::*;
+    /// let mut parser = lexopt::Parser::from_args(&["-e", "+eu", "+o", "pipefail"]);
+    /// parser.set_plus_options(true);
+    /// assert_eq!(parser.next()?, Some(Short('e')));
+    /// assert_eq!(parser.next()?, Some(Plus('e')));
+    /// assert_eq!(parser.next()?, Some(Plus('u')));
+    /// assert_eq!(parser.next()?, Some(Plus('o')));
+    /// assert_eq!(parser.value()?, "pipefail");
+    /// # Ok(()) }
+    /// ```
+    pub fn set_plus_options(&mut self, on: bool) {
+        self.plus_options = on;
+    }
+
+    /// Treat arguments like `+name` as long options, like cargo's
+    /// `+toolchain`. This is off by default, and only has an effect if
+    /// [`set_plus_options`](Parser::set_plus_options) is enabled.
+    ///
+    /// A plus sign followed by more than one character is returned as
+    /// [`PlusLong`][Arg::PlusLong] instead of being split into
+    /// [`Plus`][Arg::Plus] options, and can have a value attached with `=`.
+    /// `+x` is still `Plus('x')`.
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(), lexopt::Error> {
+    /// # use lexopt::prelude::*;
+    /// let mut parser = lexopt::Parser::from_args(&["+nightly", "+x", "build"]);
+    /// parser.set_plus_options(true);
+    /// parser.set_plus_long(true);
+    /// assert_eq!(parser.next()?, Some(PlusLong("nightly")));
+    /// assert_eq!(parser.next()?, Some(Plus('x')));
+    /// assert_eq!(parser.next()?, Some(Value("build".into())));
+    /// # Ok(()) }
+    /// ```
+    pub fn set_plus_long(&mut self, on: bool) {
+        self.plus_long = on;
+    }
 }
 
 impl Arg<'_> {
@@ -836,9 +1991,113 @@ impl Arg<'_> {
         match self {
             Arg::Short(short) => Error::UnexpectedOption(format!("-{}", short)),
             Arg::Long(long) => Error::UnexpectedOption(format!("--{}", long)),
+            Arg::Plus(plus) => Error::UnexpectedOption(format!("+{}", plus)),
+            Arg::PlusLong(long) => Error::UnexpectedOption(format!("++{}", long)),
             Arg::Value(value) => Error::UnexpectedArgument(value),
         }
     }
+
+    /// Convert an unexpected argument into an error, with a suggestion for
+    /// a similar known long option if there is one.
+    ///
+    /// `known` contains the names of the long options without the leading
+    /// dashes. Only long options get a suggestion. If there is one the error
+    /// is an [`Error::MisspelledOption`], otherwise it's the same as
+    /// [`Arg::unexpected`].
+    ///
+    /// # Example
+    /// ```
+    /// # use lexopt::prelude::*;
+    /// let err = Long("verbos").unexpected_among(&["verbose", "version", "quiet"]);
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "invalid option '--verbos'; did you mean '--verbose'?",
+    /// );
+    /// match err {
+    ///     lexopt::Error::MisspelledOption { suggestion, .. } => {
+    ///         assert_eq!(suggestion, "--verbose");
+    ///     }
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    pub fn unexpected_among(self, known: &[&str]) -> Error {
+        let suggestion = match self {
+            Arg::Long(long) => suggest(long, known).map(|known| format!("--{}", known)),
+            _ => None,
+        };
+        match (self.unexpected(), suggestion) {
+            (Error::UnexpectedOption(option), Some(suggestion)) => Error::MisspelledOption {
+                option,
+                suggestion,
+                position: None,
+            },
+            (err, _) => err,
+        }
+    }
+}
+
+/// Find the most similar candidate, if any of them are similar enough.
+fn suggest<'a>(name: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    // This is the same threshold clap uses.
+    const THRESHOLD: f64 = 0.7;
+    let mut best = None;
+    let mut best_score = THRESHOLD;
+    for &candidate in candidates {
+        let score = jaro(name, candidate);
+        if score > best_score {
+            best = Some(candidate);
+            best_score = score;
+        }
+    }
+    best
+}
+
+/// The [Jaro similarity](https://en.wikipedia.org/wiki/Jaro%E2%80%93Winkler_distance)
+/// of two strings, between 0 (nothing in common) and 1 (equal).
+fn jaro(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let window = (a.len().max(b.len()) / 2).saturating_sub(1);
+    let mut a_matched = vec![false; a.len()];
+    let mut b_matched = vec![false; b.len()];
+    let mut matches = 0;
+    for (i, &ch) in a.iter().enumerate() {
+        let start = i.saturating_sub(window);
+        let end = (i + window + 1).min(b.len());
+        for j in start..end {
+            if !b_matched[j] && b[j] == ch {
+                a_matched[i] = true;
+                b_matched[j] = true;
+                matches += 1;
+                break;
+            }
+        }
+    }
+    if matches == 0 {
+        return 0.0;
+    }
+    let a_chars = a
+        .iter()
+        .zip(&a_matched)
+        .filter(|&(_, &m)| m)
+        .map(|(ch, _)| ch);
+    let b_chars = b
+        .iter()
+        .zip(&b_matched)
+        .filter(|&(_, &m)| m)
+        .map(|(ch, _)| ch);
+    let transpositions = a_chars.zip(b_chars).filter(|&(x, y)| x != y).count() / 2;
+    let matches = matches as f64;
+    (matches / a.len() as f64
+        + matches / b.len() as f64
+        + (matches - transpositions as f64) / matches)
+        / 3.0
 }
 
 /// An iterator for multiple option-arguments, returned by [`Parser::values`].
@@ -931,6 +2190,9 @@ impl RawArgs<'_> {
 // variants will turn out to be needed: this seems reasonable, if the scope
 // of the library doesn't change. Worst case scenario it can be stuffed inside
 // Error::Custom.
+// (The scope did change in 0.4.0, which added several variants as a
+// breaking change. Later additions should again go through Error::Custom
+// or wait for the next breaking release.)
 pub enum Error {
     /// An option argument was expected but was not found.
     MissingValue {
@@ -965,10 +2227,179 @@ pub enum Error {
     /// This can be returned by the methods on [`ValueExt`].
     NonUnicodeValue(OsString),
 
+    /// An unexpected option was found that's similar to a known option.
+    /// Returned by [`Arg::unexpected_among`].
+    MisspelledOption {
+        /// The option.
+        option: String,
+        /// The similar known option.
+        suggestion: String,
+        /// The position of the option, see [`Error::with_position`].
+        position: Option<Position>,
+    },
+
+    /// An abbreviated long option matched more than one known option.
+    ///
+    /// Returned by [`Parser::next`] if [`Parser::set_long_options`] was used.
+    AmbiguousOption {
+        /// The option as given on the command line.
+        option: String,
+        /// The known options it could be an abbreviation of.
+        candidates: Vec<String>,
+        /// The position of the option.
+        position: Option<Position>,
+    },
+
+    /// A positional argument did not name a subcommand. Returned by
+    /// [`Subcommands::dispatch`].
+    UnknownSubcommand {
+        /// The argument.
+        subcommand: OsString,
+        /// The valid subcommands.
+        known: Vec<String>,
+        /// The position of the argument.
+        position: Option<Position>,
+    },
+
+    /// An abbreviated subcommand matched more than one subcommand. Returned
+    /// by [`Subcommands::dispatch`].
+    AmbiguousSubcommand {
+        /// The subcommand as given on the command line.
+        subcommand: String,
+        /// The subcommands it could be an abbreviation of.
+        candidates: Vec<String>,
+        /// The position of the argument.
+        position: Option<Position>,
+    },
+
+    /// An option that's not in the optstring was found. Returned by
+    /// [`getopt::Getopt`].
+    InvalidOption {
+        /// The option, without the dash.
+        option: char,
+        /// The position of the option.
+        position: Option<Position>,
+    },
+
+    /// An option that needs a value was found at the end of the command
+    /// line. Returned by [`getopt::Getopt`].
+    RequiresArgument {
+        /// The option, without the dash.
+        option: char,
+        /// The position of the option.
+        position: Option<Position>,
+    },
+
+    /// A required option or positional argument wasn't given. Returned by
+    /// the `docopt` module and by `from_parser`.
+    MissingArgument {
+        /// The name of the argument, like `--out` or `<input>`.
+        name: String,
+    },
+
+    /// A response file could not be expanded. Returned by
+    /// [`Parser::expand_response_files`].
+    ResponseFile {
+        /// The response file that failed.
+        path: PathBuf,
+        /// The line in the file where the problem was found, if applicable.
+        line: Option<usize>,
+        /// The underlying error.
+        error: Box<dyn std::error::Error + Send + Sync + 'static>,
+    },
+
+    /// A value from an environment variable was invalid. Returned by
+    /// [`env::Fallback`] and [`env::Value`], and by [`Parser`] for arguments
+    /// that came from [`Parser::from_env_and_var`].
+    Env {
+        /// The name of the environment variable.
+        var: String,
+        /// The error for the value.
+        error: Box<Error>,
+    },
+
     /// For custom error messages in application code.
     Custom(Box<dyn std::error::Error + Send + Sync + 'static>),
 }
 
+impl Error {
+    /// Attach a position to the error, if it's one of the variants that has
+    /// a `position` field. Other errors are returned unchanged.
+    ///
+    /// This is meant to be used together with [`Parser::current_position`]
+    /// and [`Arg::unexpected_among`], which can't know the position on its
+    /// own.
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(), lexopt::Error> {
+    /// # use lexopt::prelude::*;
+    /// let mut parser = lexopt::Parser::from_iter(&["myapp", "-x", "--verbos"]);
+    /// let mut errors = Vec::new();
+    /// while let Some(arg) = parser.next()? {
+    ///     match arg {
+    ///         Short('x') => (),
+    ///         _ => {
+    ///             let error = arg.unexpected_among(&["verbose"]);
+    ///             errors.push(error.with_position(parser.current_position()));
+    ///         }
+    ///     }
+    /// }
+    /// let position = errors[0].position().unwrap();
+    /// assert_eq!((position.index, position.offset, position.len), (2, 0, 8));
+    /// # Ok(()) }
+    /// ```
+    pub fn with_position(mut self, position: Option<Position>) -> Error {
+        self.set_position(position);
+        self
+    }
+
+    fn set_position(&mut self, position: Option<Position>) {
+        match *self {
+            Error::MisspelledOption {
+                position: ref mut slot,
+                ..
+            }
+            | Error::AmbiguousOption {
+                position: ref mut slot,
+                ..
+            }
+            | Error::UnknownSubcommand {
+                position: ref mut slot,
+                ..
+            }
+            | Error::AmbiguousSubcommand {
+                position: ref mut slot,
+                ..
+            }
+            | Error::InvalidOption {
+                position: ref mut slot,
+                ..
+            }
+            | Error::RequiresArgument {
+                position: ref mut slot,
+                ..
+            } => *slot = position,
+            Error::Env { ref mut error, .. } => error.set_position(position),
+            _ => (),
+        }
+    }
+
+    /// The position of the argument that caused the error, if known.
+    pub fn position(&self) -> Option<Position> {
+        match *self {
+            Error::MisspelledOption { position, .. }
+            | Error::AmbiguousOption { position, .. }
+            | Error::UnknownSubcommand { position, .. }
+            | Error::AmbiguousSubcommand { position, .. }
+            | Error::InvalidOption { position, .. }
+            | Error::RequiresArgument { position, .. } => position,
+            Error::Env { ref error, .. } => error.position(),
+            _ => None,
+        }
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use crate::Error::*;
@@ -980,6 +2411,13 @@ impl Display for Error {
                 write!(f, "missing argument for option '{}'", option)
             }
             UnexpectedOption(option) => write!(f, "invalid option '{}'", option),
+            MisspelledOption {
+                option, suggestion, ..
+            } => write!(
+                f,
+                "invalid option '{}'; did you mean '{}'?",
+                option, suggestion
+            ),
             UnexpectedArgument(value) => write!(f, "unexpected argument {:?}", value),
             UnexpectedValue { option, value } => {
                 write!(
@@ -988,10 +2426,83 @@ impl Display for Error {
                     option, value
                 )
             }
+            AmbiguousOption {
+                option, candidates, ..
+            } => {
+                write!(f, "option '{}' is ambiguous; possibilities:", option)?;
+                for candidate in candidates {
+                    write!(f, " '{}'", candidate)?;
+                }
+                Ok(())
+            }
+            UnknownSubcommand {
+                subcommand, known, ..
+            } => {
+                write!(f, "unknown subcommand {:?}", subcommand)?;
+                if !known.is_empty() {
+                    f.write_str("; valid subcommands:")?;
+                    for name in known {
+                        write!(f, " '{}'", name)?;
+                    }
+                }
+                Ok(())
+            }
+            AmbiguousSubcommand {
+                subcommand,
+                candidates,
+                ..
+            } => {
+                write!(
+                    f,
+                    "subcommand '{}' is ambiguous; possibilities:",
+                    subcommand
+                )?;
+                for candidate in candidates {
+                    write!(f, " '{}'", candidate)?;
+                }
+                Ok(())
+            }
+            InvalidOption { option, .. } => write!(f, "invalid option -- '{}'", option),
+            RequiresArgument { option, .. } => {
+                write!(f, "option requires an argument -- '{}'", option)
+            }
+            MissingArgument { name } => write!(f, "missing required argument '{}'", name),
             NonUnicodeValue(value) => write!(f, "argument is invalid unicode: {:?}", value),
             ParsingFailed { value, error } => {
                 write!(f, "cannot parse argument {:?}: {}", value, error)
             }
+            ResponseFile {
+                path,
+                line: Some(line),
+                error,
+            } => write!(
+                f,
+                "error in response file '{}' on line {}: {}",
+                path.display(),
+                line,
+                error
+            ),
+            ResponseFile {
+                path,
+                line: None,
+                error,
+            } if error.is::<std::io::Error>() => write!(
+                f,
+                "cannot read response file '{}': {}",
+                path.display(),
+                error
+            ),
+            ResponseFile {
+                path,
+                line: None,
+                error,
+            } => write!(
+                f,
+                "cannot expand response file '{}': {}",
+                path.display(),
+                error
+            ),
+            Env { var, error } => write!(f, "in ${}: {}", var, error),
             Custom(err) => write!(f, "{}", err),
         }
     }
@@ -1007,7 +2518,10 @@ impl std::fmt::Debug for Error {
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            Error::ParsingFailed { error, .. } | Error::Custom(error) => Some(error.as_ref()),
+            Error::ParsingFailed { error, .. }
+            | Error::ResponseFile { error, .. }
+            | Error::Custom(error) => Some(error.as_ref()),
+            Error::Env { error, .. } => Some(error.as_ref()),
             _ => None,
         }
     }
@@ -1025,6 +2539,12 @@ impl<'a> From<&'a str> for Error {
     }
 }
 
+impl From<shell::SplitError> for Error {
+    fn from(err: shell::SplitError) -> Self {
+        Error::Custom(Box::new(err))
+    }
+}
+
 /// For [`OsString::into_string`], so it may be used with the try (`?`) operator.
 ///
 /// [`ValueExt::string`] is the new preferred method because it's compatible with
@@ -1038,6 +2558,7 @@ impl From<OsString> for Error {
 mod private {
     pub trait Sealed {}
     impl Sealed for std::ffi::OsString {}
+    impl Sealed for crate::env::Value {}
 }
 
 /// An optional extension trait with methods for parsing [`OsString`]s.
@@ -1117,6 +2638,48 @@ impl ValueExt for OsString {
     }
 }
 
+/// A type that can be parsed from command line arguments.
+///
+/// This is usually implemented with `#[derive(lexopt::Parse)]`, which writes
+/// the `Parser::next()` loop for a struct. It requires the `derive`
+/// feature. See the [`lexopt-derive`](https://docs.rs/lexopt-derive) crate
+/// for the attributes it understands.
+///
+/// # Example
+/// ```
+/// # fn main() -> Result<(), lexopt::Error> {
+/// use lexopt::Parse;
+///
+/// #[derive(lexopt::Parse)]
+/// struct Args {
+///     #[lexopt(positional)]
+///     thing: String,
+///     #[lexopt(short, default = 1)]
+///     number: u32,
+///     shout: bool,
+/// }
+///
+/// let mut parser = lexopt::Parser::from_iter(&["hello", "-n3", "world"]);
+/// let args = Args::from_parser(&mut parser)?;
+/// assert_eq!(args.thing, "world");
+/// assert_eq!(args.number, 3);
+/// assert!(!args.shout);
+/// # Ok(()) }
+/// ```
+#[cfg(feature = "derive")]
+pub trait Parse: Sized {
+    /// Parse the remaining arguments of `parser`.
+    fn from_parser(parser: &mut Parser) -> Result<Self, Error>;
+
+    /// Parse the command line arguments of the current process.
+    fn from_env() -> Result<Self, Error> {
+        Self::from_parser(&mut Parser::from_env())
+    }
+}
+
+#[cfg(feature = "derive")]
+pub use lexopt_derive::Parse;
+
 /// A small prelude for processing arguments.
 ///
 /// It allows you to write `Short`/`Long`/`Value` without an [`Arg`] prefix
@@ -1137,6 +2700,138 @@ pub mod prelude {
     pub use super::ValueExt;
 }
 
+/// Whether the first byte of an argument (or UTF-16 code unit, on Windows) is `byte`.
+fn starts_with_byte(arg: &OsStr, byte: u8) -> bool {
+    #[cfg(unix)]
+    let first = arg.as_bytes().first().map(|&b| u16::from(b));
+    #[cfg(windows)]
+    let first = arg.encode_wide().next();
+    #[cfg(not(any(unix, windows)))]
+    let first = arg
+        .to_string_lossy()
+        .as_bytes()
+        .first()
+        .map(|&b| u16::from(b));
+
+    first == Some(u16::from(byte))
+}
+
+/// Split an argument that starts with `prefix` at the first of `separators`.
+///
+/// Returns the name, which must be valid unicode, the length of the prefix
+/// and the name in position units, and the value after the separator.
+fn split_word_option(
+    arg: &OsStr,
+    prefix: u8,
+    separators: &[u8],
+) -> Option<(String, usize, Option<OsString>)> {
+    #[cfg(unix)]
+    {
+        let bytes = arg.as_bytes();
+        if bytes.first() != Some(&prefix) {
+            return None;
+        }
+        let end = bytes
+            .iter()
+            .position(|b| separators.contains(b))
+            .unwrap_or(bytes.len());
+        let name = std::str::from_utf8(&bytes[1..end]).ok()?;
+        let value = bytes
+            .get(end + 1..)
+            .map(|value| OsStr::from_bytes(value).to_owned());
+        Some((name.to_owned(), end, value))
+    }
+
+    #[cfg(windows)]
+    {
+        let units: Vec<u16> = arg.encode_wide().collect();
+        if units.first() != Some(&u16::from(prefix)) {
+            return None;
+        }
+        let end = units
+            .iter()
+            .position(|&unit| separators.iter().any(|&sep| unit == u16::from(sep)))
+            .unwrap_or(units.len());
+        let name = String::from_utf16(&units[1..end]).ok()?;
+        let value = units.get(end + 1..).map(OsString::from_wide);
+        // Positions count bytes, unless the argument is invalid unicode.
+        let name_len = if arg.to_str().is_some() {
+            name.len() + 1
+        } else {
+            end
+        };
+        Some((name, name_len, value))
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        let text = arg.to_str()?;
+        if text.as_bytes().first() != Some(&prefix) {
+            return None;
+        }
+        let end = text
+            .bytes()
+            .position(|b| separators.contains(&b))
+            .unwrap_or(text.len());
+        let value = text.get(end + 1..).map(OsString::from);
+        Some((text[1..end].to_owned(), end, value))
+    }
+}
+
+/// The default filter for [`Parser::set_slash_options`].
+///
+/// It accepts a name if it's not empty and doesn't contain a slash or a
+/// backslash, so `/help` and `/Fo` are options but `/`, `/usr/bin` and
+/// `//server/share` are not. Single-component paths like `/tmp` can't be
+/// told apart from options. If that matters, use
+/// [`Parser::set_slash_option_filter`] with a list of known options.
+pub fn slash_option_heuristic(name: &str) -> bool {
+    !name.is_empty() && !name.contains('/') && !name.contains('\\')
+}
+
+/// Whether an argument looks like a negative number, see
+/// [`Parser::set_negative_numbers`].
+fn is_negative_number(arg: &OsStr) -> bool {
+    let text = match arg.to_str() {
+        Some(text) if text.starts_with('-') => &text[1..],
+        _ => return false,
+    };
+    if ["inf", "infinity", "nan"]
+        .iter()
+        .any(|word| text.eq_ignore_ascii_case(word))
+    {
+        return true;
+    }
+
+    let bytes = text.as_bytes();
+    let mut pos = 0;
+    let digits = |pos: &mut usize| {
+        let start = *pos;
+        while bytes.get(*pos).map_or(false, u8::is_ascii_digit) {
+            *pos += 1;
+        }
+        *pos - start
+    };
+    let mut mantissa = digits(&mut pos);
+    if bytes.get(pos) == Some(&b'.') {
+        pos += 1;
+        mantissa += digits(&mut pos);
+    }
+    if mantissa == 0 {
+        return false;
+    }
+    if let Some(b'e') | Some(b'E') = bytes.get(pos) {
+        pos += 1;
+        if let Some(b'+') | Some(b'-') = bytes.get(pos) {
+            pos += 1;
+        }
+        if digits(&mut pos) == 0 {
+            return false;
+        }
+    }
+    pos == bytes.len()
+}
+
 /// Take the first codepoint from a UTF-8 bytestring.
 ///
 /// The rest of the bytestring does not have to be valid unicode.
@@ -1720,6 +3415,560 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn long_abbreviations() -> Result<(), Error> {
+        let mut p = parse("--v --verb --verbose=x --version --vers --ver=1 --vex --q --");
+        p.set_long_options(&["verbose", "version", "verbose", "v"]);
+        // Exact matches win
+        assert_eq!(p.next()?.unwrap(), Long("v"));
+        assert_eq!(p.next()?.unwrap(), Long("verbose"));
+        assert_eq!(p.next()?.unwrap(), Long("verbose"));
+        assert_eq!(p.value()?, "x");
+        assert_eq!(p.next()?.unwrap(), Long("version"));
+        assert_eq!(p.next()?.unwrap(), Long("version"));
+        match p.next().unwrap_err() {
+            Error::AmbiguousOption {
+                option, candidates, ..
+            } => {
+                assert_eq!(option, "--ver");
+                assert_eq!(candidates, &["--verbose", "--version"]);
+            }
+            err => panic!("{}", err),
+        }
+        // The value was discarded
+        assert_eq!(p.next()?.unwrap(), Long("vex"));
+        assert_eq!(p.next()?.unwrap(), Long("q"));
+        assert_eq!(p.next()?, None);
+
+        let mut p = parse("--verb --ver --vers=1");
+        p.set_long_options(&["verbose", "version"]);
+        assert_eq!(p.next()?.unwrap(), Long("verbose"));
+        assert_eq!(
+            p.next().unwrap_err().to_string(),
+            "option '--ver' is ambiguous; possibilities: '--verbose' '--version'"
+        );
+        assert_eq!(p.next()?.unwrap(), Long("version"));
+        assert_eq!(
+            p.next().unwrap_err().to_string(),
+            r#"unexpected argument for option '--version': "1""#
+        );
+
+        let mut p = parse("--verb --=x");
+        p.set_long_options(&["verbose"]);
+        p.set_long_options(&[] as &[&str]);
+        assert_eq!(p.next()?.unwrap(), Long("verb"));
+        assert_eq!(p.next()?.unwrap(), Long(""));
+        assert_eq!(p.value()?, "x");
+
+        let mut p = parse("-verb=x -ver -vx /out:a /o /ou");
+        p.set_long_options(&["verbose", "version", "output"]);
+        p.set_single_dash_long_options(&["verbose", "version"]);
+        p.set_slash_options(true);
+        assert_eq!(p.next()?.unwrap(), Long("verbose"));
+        assert_eq!(p.value()?, "x");
+        assert_eq!(
+            p.next().unwrap_err().to_string(),
+            "option '-ver' is ambiguous; possibilities: '-verbose' '-version'"
+        );
+        assert_eq!(p.next()?.unwrap(), Short('v'));
+        assert_eq!(p.next()?.unwrap(), Short('x'));
+        assert_eq!(p.next()?.unwrap(), Long("output"));
+        assert_eq!(p.value()?, "a");
+        assert_eq!(p.next()?.unwrap(), Short('o'));
+        assert_eq!(p.next()?.unwrap(), Long("output"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn negative_numbers() -> Result<(), Error> {
+        let mut p = parse("-5 --offset -5 --range -3 7 -x -- -1");
+        assert_eq!(p.next()?.unwrap(), Short('5'));
+        p.set_negative_numbers(true);
+        assert_eq!(p.next()?.unwrap(), Long("offset"));
+        assert_eq!(p.value()?, "-5");
+        assert_eq!(p.next()?.unwrap(), Long("range"));
+        assert_eq!(p.values()?.collect::<Vec<_>>(), &["-3", "7"]);
+        assert_eq!(p.next()?.unwrap(), Short('x'));
+        assert_eq!(p.next()?.unwrap(), Value("-1".into()));
+        assert_eq!(p.next()?, None);
+
+        let mut p = parse("-x -1 -y");
+        p.set_negative_numbers(true);
+        assert_eq!(p.next()?.unwrap(), Short('x'));
+        assert_eq!(p.values()?.collect::<Vec<_>>(), &["-1"]);
+        assert_eq!(p.next()?.unwrap(), Short('y'));
+
+        let mut p = parse("-x -9 -15 -25 -1.5 --num -1");
+        p.set_negative_numbers(true);
+        p.set_digit_options(vec!['1', '9']);
+        assert_eq!(p.next()?.unwrap(), Short('x'));
+        assert!(p.values().is_err());
+        assert_eq!(p.next()?.unwrap(), Short('9'));
+        assert_eq!(p.next()?.unwrap(), Short('1'));
+        assert_eq!(p.next()?.unwrap(), Short('5'));
+        assert_eq!(p.next()?.unwrap(), Value("-25".into()));
+        assert_eq!(p.next()?.unwrap(), Short('1'));
+        assert_eq!(p.next()?.unwrap(), Short('.'));
+        assert_eq!(p.next()?.unwrap(), Short('5'));
+        assert_eq!(p.next()?.unwrap(), Long("num"));
+        assert_eq!(p.value()?, "-1");
+
+        for &number in &[
+            "-0",
+            "-5",
+            "-12.5",
+            "-.5",
+            "-5.",
+            "-1e10",
+            "-1E+3",
+            "-2.5e-3",
+            "-inf",
+            "-INF",
+            "-Infinity",
+            "-nan",
+            "-NaN",
+        ] {
+            assert!(is_negative_number(number.as_ref()), "{}", number);
+        }
+        for &other in &[
+            "", "-", "--", "5", "-.", "-e5", "-.e5", "-1e", "-1e+", "-1x", "-1.2.3", "--5", "-+5",
+            "-in", "-infinit", "-0x10", "- 5",
+        ] {
+            assert!(!is_negative_number(other.as_ref()), "{}", other);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn single_dash_long() -> Result<(), Error> {
+        let mut p = parse("-name -name=x -print0=z -v -o=y --long -- -version");
+        p.set_single_dash_long(true);
+        assert_eq!(p.next()?.unwrap(), Long("name"));
+        assert_eq!(p.next()?.unwrap(), Long("name"));
+        assert_eq!(
+            p.current_position(),
+            Some(Position {
+                index: 1,
+                offset: 0,
+                len: 5
+            })
+        );
+        assert_eq!(p.attached(), Some(Attached::Equals));
+        assert_eq!(p.value()?, "x");
+        assert_eq!(p.next()?.unwrap(), Long("print0"));
+        assert_eq!(
+            p.reject_value().unwrap_err().to_string(),
+            "unexpected argument for option '-print0': \"z\""
+        );
+        assert_eq!(p.next()?.unwrap(), Short('v'));
+        assert_eq!(p.next()?.unwrap(), Short('o'));
+        assert_eq!(p.value()?, "y");
+        assert_eq!(p.next()?.unwrap(), Long("long"));
+        assert_eq!(p.next()?.unwrap(), Value("-version".into()));
+        assert_eq!(p.next()?, None);
+
+        let mut p = parse("-version -vx -help=x -v");
+        p.set_single_dash_long_options(&["version", "help"]);
+        assert_eq!(p.next()?.unwrap(), Long("version"));
+        assert_eq!(p.next()?.unwrap(), Short('v'));
+        assert_eq!(p.next()?.unwrap(), Short('x'));
+        assert_eq!(p.next()?.unwrap(), Long("help"));
+        assert_eq!(p.value()?, "x");
+        p.set_single_dash_long_options(&[] as &[&str]);
+        assert_eq!(p.next()?.unwrap(), Short('v'));
+
+        let mut p = parse("-size");
+        p.set_single_dash_long(true);
+        assert_eq!(p.next()?.unwrap(), Long("size"));
+        assert_eq!(
+            p.value().unwrap_err().to_string(),
+            "missing argument for option '-size'"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn plus_options() -> Result<(), Error> {
+        let mut p = parse("+x -x +abc +ofoo +o=bar ++long=x ++ + +o");
+        assert_eq!(p.next()?.unwrap(), Value("+x".into()));
+        p.set_plus_options(true);
+        assert_eq!(p.next()?.unwrap(), Short('x'));
+        assert_eq!(p.next()?.unwrap(), Plus('a'));
+        assert_eq!(p.next()?.unwrap(), Plus('b'));
+        assert_eq!(p.next()?.unwrap(), Plus('c'));
+        assert_eq!(p.next()?.unwrap(), Plus('o'));
+        assert_eq!(p.attached(), Some(Attached::Adjacent));
+        assert_eq!(p.value()?, "foo");
+        assert_eq!(p.next()?.unwrap(), Plus('o'));
+        assert_eq!(
+            p.next().unwrap_err().to_string(),
+            "unexpected argument for option '+o': \"bar\""
+        );
+        assert_eq!(p.next()?.unwrap(), PlusLong("long"));
+        assert_eq!(
+            p.current_position(),
+            Some(Position {
+                index: 5,
+                offset: 0,
+                len: 6
+            })
+        );
+        assert_eq!(p.value()?, "x");
+        assert_eq!(p.next()?.unwrap(), Value("++".into()));
+        assert_eq!(p.next()?.unwrap(), Value("+".into()));
+        assert_eq!(p.next()?.unwrap(), Plus('o'));
+        assert_eq!(
+            p.value().unwrap_err().to_string(),
+            "missing argument for option '+o'"
+        );
+
+        let mut p = parse("-a 1 +b 2 +nightly +nightly=x +c ++long");
+        p.set_plus_options(true);
+        p.set_long_options(&["longer"]);
+        assert_eq!(p.next()?.unwrap(), Short('a'));
+        assert_eq!(p.values()?.collect::<Vec<_>>(), &["1"]);
+        assert_eq!(p.next()?.unwrap(), Plus('b'));
+        assert_eq!(p.values()?.collect::<Vec<_>>(), &["2"]);
+        p.set_plus_long(true);
+        assert_eq!(p.next()?.unwrap(), PlusLong("nightly"));
+        assert_eq!(p.next()?.unwrap(), PlusLong("nightly"));
+        assert_eq!(
+            p.next().unwrap_err().to_string(),
+            "unexpected argument for option '+nightly': \"x\""
+        );
+        assert_eq!(p.next()?.unwrap(), Plus('c'));
+        assert_eq!(p.next()?.unwrap(), PlusLong("long"));
+
+        assert_eq!(Plus('x').unexpected().to_string(), "invalid option '+x'");
+        assert_eq!(
+            PlusLong("x").unexpected().to_string(),
+            "invalid option '++x'"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn negation() -> Result<(), Error> {
+        let mut p = parse("--no-color --color --no-color=x --no-pager --no- -c --no-col");
+        assert_eq!(p.next()?.unwrap(), Long("no-color"));
+        assert!(!p.negated());
+        p.set_negatable_options(&["color", "pager-"]);
+        p.set_long_options(&["color"]);
+        assert_eq!(p.next()?.unwrap(), Long("color"));
+        assert!(!p.negated());
+        assert_eq!(p.next()?.unwrap(), Long("color"));
+        assert!(p.negated());
+        assert_eq!(
+            p.current_position(),
+            Some(Position {
+                index: 2,
+                offset: 0,
+                len: 10
+            })
+        );
+        assert_eq!(
+            p.next().unwrap_err().to_string(),
+            "unexpected argument for option '--no-color': \"x\""
+        );
+        assert_eq!(p.next()?.unwrap(), Long("no-pager"));
+        assert!(!p.negated());
+        assert_eq!(p.next()?.unwrap(), Long("no-"));
+        assert_eq!(p.next()?.unwrap(), Short('c'));
+        assert!(!p.negated());
+        assert_eq!(p.next()?.unwrap(), Long("no-col"));
+
+        let mut p = parse("--no-color");
+        p.set_negatable_options(&["color"]);
+        p.set_negatable_options(&[] as &[&str]);
+        assert_eq!(p.next()?.unwrap(), Long("no-color"));
+        Ok(())
+    }
+
+    #[test]
+    fn slash_options() -> Result<(), Error> {
+        let mut p = parse("/v /help /out:a.exe /D=X /W4:x /usr/bin / -x /ab a b -- /c");
+        assert_eq!(p.next()?.unwrap(), Value("/v".into()));
+        p.set_slash_options(true);
+        assert_eq!(p.next()?.unwrap(), Long("help"));
+        assert_eq!(p.next()?.unwrap(), Long("out"));
+        assert_eq!(p.attached(), Some(Attached::Equals));
+        assert_eq!(p.value()?, "a.exe");
+        assert_eq!(p.next()?.unwrap(), Short('D'));
+        assert_eq!(
+            p.current_position(),
+            Some(Position {
+                index: 3,
+                offset: 0,
+                len: 2
+            })
+        );
+        assert_eq!(p.value()?, "X");
+        assert_eq!(p.next()?.unwrap(), Long("W4"));
+        assert_eq!(
+            p.next().unwrap_err().to_string(),
+            "unexpected argument for option '/W4': \"x\""
+        );
+        assert_eq!(p.next()?.unwrap(), Value("/usr/bin".into()));
+        assert_eq!(p.next()?.unwrap(), Value("/".into()));
+        assert_eq!(p.next()?.unwrap(), Short('x'));
+        assert_eq!(p.next()?.unwrap(), Long("ab"));
+        assert_eq!(p.values()?.collect::<Vec<_>>(), &["a", "b"]);
+        assert_eq!(p.next()?.unwrap(), Value("/c".into()));
+        assert_eq!(p.next()?, None);
+
+        let mut p = parse("/out /x /x");
+        p.set_slash_options(true);
+        assert_eq!(p.next()?.unwrap(), Long("out"));
+        assert_eq!(
+            p.values().unwrap_err().to_string(),
+            "missing argument for option '/out'"
+        );
+        p.set_slash_option_filter(|name| name == "out");
+        assert_eq!(p.next()?.unwrap(), Value("/x".into()));
+        p.set_slash_options(false);
+        assert_eq!(p.next()?.unwrap(), Value("/x".into()));
+
+        let mut p = parse("/out:x -out:y");
+        p.set_slash_options(true);
+        p.set_single_dash_long(true);
+        assert_eq!(p.next()?.unwrap(), Long("out"));
+        assert_eq!(p.value()?, "x");
+        assert_eq!(p.next()?.unwrap(), Long("out:y"));
+
+        assert!(slash_option_heuristic("Fo"));
+        for &other in &["", "usr/bin", "/server/share", r"a\b"] {
+            assert!(!slash_option_heuristic(other), "{}", other);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn optional_values() -> Result<(), Error> {
+        let mut p = parse("--color --color= --color=x -c -c=x -cx -abc -- --x=y");
+        assert_eq!(p.attached(), None);
+        assert_eq!(p.next()?.unwrap(), Long("color"));
+        assert_eq!(p.attached(), None);
+        p.reject_value()?;
+        assert_eq!(p.optional_value(), None);
+        assert_eq!(p.next()?.unwrap(), Long("color"));
+        assert_eq!(p.attached(), Some(Attached::Equals));
+        assert_eq!(p.optional_value().unwrap(), "");
+        assert_eq!(p.attached(), None);
+        assert_eq!(p.next()?.unwrap(), Long("color"));
+        assert_eq!(
+            p.reject_value().unwrap_err().to_string(),
+            r#"unexpected argument for option '--color': "x""#
+        );
+        // The value was consumed
+        assert_eq!(p.next()?.unwrap(), Short('c'));
+        assert_eq!(p.attached(), None);
+        assert_eq!(p.next()?.unwrap(), Short('c'));
+        assert_eq!(p.attached(), Some(Attached::Equals));
+        let err = p.reject_value().unwrap_err();
+        assert_eq!(p.error_position(&err).unwrap().offset, 3);
+        match err {
+            Error::UnexpectedValue { option, value } => {
+                assert_eq!(option, "-c");
+                assert_eq!(value, "x");
+            }
+            err => panic!("{}", err),
+        }
+        assert_eq!(p.next()?.unwrap(), Short('c'));
+        assert_eq!(p.attached(), Some(Attached::Adjacent));
+        assert_eq!(p.optional_value().unwrap(), "x");
+        assert_eq!(p.next()?.unwrap(), Short('a'));
+        assert_eq!(p.attached(), Some(Attached::Adjacent));
+        p.reject_value()?;
+        assert_eq!(p.next()?.unwrap(), Short('b'));
+        assert_eq!(p.next()?.unwrap(), Short('c'));
+        assert_eq!(p.attached(), None);
+        assert_eq!(p.next()?.unwrap(), Value("--x=y".into()));
+        assert_eq!(p.attached(), None);
+        p.reject_value()?;
+
+        let mut p = parse("-c=x -c= -c -x -c==");
+        p.set_getopt_optional_values(true);
+        assert_eq!(p.next()?.unwrap(), Short('c'));
+        assert_eq!(p.attached(), Some(Attached::Equals));
+        assert_eq!(p.optional_value().unwrap(), "=x");
+        assert_eq!(p.next()?.unwrap(), Short('c'));
+        assert_eq!(p.optional_value().unwrap(), "=");
+        assert_eq!(p.next()?.unwrap(), Short('c'));
+        assert_eq!(p.optional_value(), None);
+        assert_eq!(p.next()?.unwrap(), Short('x'));
+        assert_eq!(p.next()?.unwrap(), Short('c'));
+        // value() still strips the = sign
+        assert_eq!(p.value()?, "=");
+
+        let mut p = parse("-c=x");
+        p.set_short_equals(false);
+        assert_eq!(p.next()?.unwrap(), Short('c'));
+        assert_eq!(p.attached(), Some(Attached::Adjacent));
+        p.reject_value()?;
+        assert_eq!(p.next()?.unwrap(), Short('='));
+        Ok(())
+    }
+
+    #[test]
+    fn positions() -> Result<(), Error> {
+        fn pos(index: usize, offset: usize, len: usize) -> Option<Position> {
+            Some(Position { index, offset, len })
+        }
+
+        let mut p = Parser::from_iter(&[
+            "bin",
+            "-ab",
+            "--foo=bar",
+            "baz",
+            "-cval",
+            "-d=x",
+            "--",
+            "-e",
+        ]);
+        assert_eq!(p.current_position(), None);
+        assert_eq!(p.next()?.unwrap(), Short('a'));
+        assert_eq!(p.current_position(), pos(1, 1, 1));
+        assert_eq!(p.next()?.unwrap(), Short('b'));
+        assert_eq!(p.current_position(), pos(1, 2, 1));
+        assert_eq!(p.next()?.unwrap(), Long("foo"));
+        assert_eq!(p.current_position(), pos(2, 0, 5));
+        assert_eq!(p.optional_value().unwrap(), "bar");
+        assert_eq!(p.current_position(), pos(2, 6, 3));
+        assert_eq!(p.next()?.unwrap(), Value("baz".into()));
+        assert_eq!(p.current_position(), pos(3, 0, 3));
+        assert_eq!(p.next()?.unwrap(), Short('c'));
+        assert_eq!(p.values()?.collect::<Vec<_>>(), &["val"]);
+        assert_eq!(p.current_position(), pos(4, 2, 3));
+        assert_eq!(p.next()?.unwrap(), Short('d'));
+        let err = p.next().unwrap_err();
+        assert_eq!(p.error_position(&err), pos(5, 3, 1));
+        match err {
+            Error::UnexpectedValue { value, .. } => assert_eq!(value, "x"),
+            err => panic!("{}", err),
+        }
+        assert_eq!(p.next()?.unwrap(), Value("-e".into()));
+        assert_eq!(p.current_position(), pos(7, 0, 2));
+        assert_eq!(p.next()?, None);
+        assert_eq!(p.current_position(), pos(7, 0, 2));
+
+        let mut p = Parser::from_args(&["x", "-µ", "--long", "--ab=c"]);
+        p.set_long_options(&["abc", "abd"]);
+        assert_eq!(p.value()?, "x");
+        assert_eq!(p.current_position(), pos(0, 0, 1));
+        assert_eq!(p.next()?.unwrap(), Short('µ'));
+        assert_eq!(p.current_position(), pos(1, 1, 2));
+        assert_eq!(p.next()?.unwrap(), Long("long"));
+        assert_eq!(p.position, pos(2, 0, 6));
+        assert_eq!(p.next().unwrap_err().position(), pos(3, 0, 4));
+        let err = p.value().unwrap_err();
+        assert_eq!(p.error_position(&err), pos(2, 0, 6));
+        match err {
+            Error::MissingValue { option } => assert_eq!(option.unwrap(), "--long"),
+            err => panic!("{}", err),
+        }
+        let err = Short('x').unexpected();
+        assert_eq!(err.position(), None);
+        assert_eq!(p.error_position(&err), pos(3, 0, 4));
+
+        let err = Long("verbos").unexpected_among(&["verbose"]);
+        assert_eq!(err.position(), None);
+        assert_eq!(err.with_position(pos(1, 2, 3)).position(), pos(1, 2, 3));
+        let err = Error::from("custom").with_position(pos(1, 2, 3));
+        assert_eq!(err.position(), None);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn positions_invalid() -> Result<(), Error> {
+        let mut p = Parser::from_args(vec![
+            OsStr::from_bytes(b"-\xFFa"),
+            OsStr::from_bytes(b"--\xFF=\xFF"),
+        ]);
+        assert_eq!(p.next()?.unwrap(), Short('�'));
+        assert_eq!(p.current_position().unwrap().offset, 1);
+        assert_eq!(p.current_position().unwrap().len, 1);
+        assert_eq!(p.next()?.unwrap(), Short('a'));
+        assert_eq!(p.current_position().unwrap().offset, 2);
+        assert_eq!(p.next()?.unwrap(), Long("�"));
+        // The lossy version is longer than the original
+        assert_eq!(p.current_position().unwrap().len, 3);
+        assert_eq!(p.value()?, OsStr::from_bytes(b"\xFF"));
+        assert_eq!(p.current_position().unwrap().offset, 4);
+        Ok(())
+    }
+
+    #[test]
+    fn env_args() -> Result<(), Error> {
+        let mut p = Parser::from_iter(&["bin", "-b", "x"]);
+        p.set_keep_command_line(true);
+        p.prepend_args("FLAGS", "-a 'one two' --long=val".as_ref())?;
+        assert_eq!(
+            p.command_line.as_ref().unwrap().1,
+            &["bin", "-a", "one two", "--long=val", "-b", "x"]
+        );
+        assert_eq!(p.next()?.unwrap(), Short('a'));
+        assert_eq!(p.env_var_at(p.current_position().unwrap()), Some("FLAGS"));
+        assert_eq!(p.value()?, "one two");
+        assert_eq!(p.current_position().unwrap().index, 2);
+        assert_eq!(p.next()?.unwrap(), Long("long"));
+        let err = p.next().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "in $FLAGS: unexpected argument for option '--long': \"val\"",
+        );
+        assert_eq!(p.error_position(&err).unwrap().index, 3);
+        assert_eq!(p.next()?.unwrap(), Short('b'));
+        assert_eq!(p.env_var_at(p.current_position().unwrap()), None);
+        assert_eq!(p.value()?, "x");
+        assert_eq!(p.next()?, None);
+
+        let mut p = Parser::from_args(&["-b"]);
+        p.prepend_args("FLAGS", "--ab -a".as_ref())?;
+        p.set_long_options(&["abc", "abd"]);
+        assert_eq!(
+            p.next().unwrap_err().to_string(),
+            "in $FLAGS: option '--ab' is ambiguous; possibilities: '--abc' '--abd'",
+        );
+        assert_eq!(p.next()?.unwrap(), Short('a'));
+        assert_eq!(p.current_position().unwrap().index, 1);
+        // The value comes from the command line, so it's not the variable's fault
+        assert_eq!(p.value()?, "-b");
+
+        let mut p = Parser::from_args(&["-b"]);
+        p.prepend_args("FLAGS", "-a".as_ref())?;
+        p.next()?;
+        p.next()?;
+        assert_eq!(
+            p.value().unwrap_err().to_string(),
+            "missing argument for option '-b'"
+        );
+        let mut p = Parser::from_args(&["-x"]);
+        p.prepend_args("FLAGS", "-a".as_ref())?;
+        p.next()?;
+        match p.values().unwrap_err() {
+            Error::Env { var, error } => {
+                assert_eq!(var, "FLAGS");
+                assert_eq!(error.to_string(), "missing argument for option '-a'");
+            }
+            err => panic!("{}", err),
+        }
+
+        let mut p = Parser::from_iter(&["bin"]);
+        p.prepend_args("FLAGS", "".as_ref())?;
+        assert_eq!(p.next()?, None);
+        assert_eq!(
+            p.prepend_args("FLAGS", "'x".as_ref())
+                .unwrap_err()
+                .to_string(),
+            "in $FLAGS: unterminated single quote at offset 0",
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn bin_name() {
         assert_eq!(
@@ -1827,6 +4076,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_suggestions() {
+        let known = &["verbose", "version", "color", "colour", "no-verify"];
+        assert_eq!(suggest("verbos", known), Some("verbose"));
+        assert_eq!(suggest("verison", known), Some("version"));
+        assert_eq!(suggest("colr", known), Some("color"));
+        assert_eq!(suggest("no-verfy", known), Some("no-verify"));
+        assert_eq!(suggest("x", known), None);
+        assert_eq!(suggest("", known), None);
+        assert_eq!(suggest("verbose", &[]), None);
+
+        assert_eq!(
+            Long("verbos").unexpected_among(known).to_string(),
+            "invalid option '--verbos'; did you mean '--verbose'?",
+        );
+        assert_eq!(
+            Long("frobnicate").unexpected_among(known).to_string(),
+            "invalid option '--frobnicate'",
+        );
+        assert_eq!(
+            Short('v').unexpected_among(known).to_string(),
+            "invalid option '-v'",
+        );
+        assert_eq!(
+            Value("verbose".into()).unexpected_among(known).to_string(),
+            r#"unexpected argument "verbose""#,
+        );
+
+        assert_eq!(jaro("", ""), 1.0);
+        assert_eq!(jaro("abc", "abc"), 1.0);
+        assert_eq!(jaro("abc", "xyz"), 0.0);
+        assert!((jaro("martha", "marhta") - 0.944).abs() < 0.001);
+        assert!((jaro("dixon", "dicksonx") - 0.767).abs() < 0.001);
+    }
+
     #[test]
     fn test_first_codepoint() {
         assert_eq!(first_codepoint(b"foo").unwrap(), Some('f'));
@@ -1984,7 +4268,12 @@ mod tests {
             panic!("Stuck in loop");
         }
 
-        // has_pending() == optional_value().is_some()
+        if let Some(position) = parser.current_position() {
+            assert!(position.index < parser.end_index);
+        }
+
+        // has_pending() == optional_value().is_some() == attached().is_some()
+        assert_eq!(parser.attached().is_some(), parser.has_pending());
         if parser.has_pending() {
             {
                 let mut parser = parser.clone();
@@ -2009,7 +4298,7 @@ mod tests {
             assert!(parser.try_raw_args().is_some());
             // Verify state transitions
             match prev_state {
-                State::None | State::PendingValue(_) => {
+                State::None | State::PendingValue(..) => {
                     assert_matches!(parser.state, State::None);
                 }
                 State::Shorts(arg, pos) => {
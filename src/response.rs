@@ -0,0 +1,494 @@
+//! Expansion of response files (`@file` arguments).
+//!
+//! This lives in its own module because it's the only part of the crate that
+//! touches the filesystem.
+
+use std::{
+    ffi::{OsStr, OsString},
+    io,
+    path::{Path, PathBuf},
+};
+
+#[cfg(unix)]
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+#[cfg(windows)]
+use std::os::windows::ffi::OsStrExt;
+
+use crate::Error;
+
+/// The syntax used to split a response file into arguments.
+///
+/// Used by [`Parser::expand_response_files`][crate::Parser::expand_response_files].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFileSyntax {
+    /// The syntax used by GCC and other GNU tools.
+    ///
+    /// Arguments are separated by whitespace (including newlines). Single
+    /// and double quotes group characters into a single argument, and a
+    /// backslash escapes the next character, even inside quotes.
+    Gnu,
+    /// The syntax used by MSVC and other Windows tools.
+    ///
+    /// Arguments are separated by whitespace (including newlines). Double
+    /// quotes group characters into a single argument. Backslashes are only
+    /// special in front of a double quote: `2n` backslashes followed by a
+    /// quote become `n` backslashes and start or end a quoted section,
+    /// `2n + 1` backslashes followed by a quote become `n` backslashes and a
+    /// literal quote. Inside a quoted section `""` is a literal quote.
+    Msvc,
+}
+
+struct Expander {
+    syntax: ResponseFileSyntax,
+    max_depth: usize,
+    /// Canonicalized paths of the files we're currently inside of.
+    stack: Vec<PathBuf>,
+    /// Whether we've seen a `--`.
+    finished: bool,
+    out: Vec<OsString>,
+}
+
+/// Expand all the response files in `args`.
+pub(crate) fn expand(
+    args: &[OsString],
+    syntax: ResponseFileSyntax,
+    max_depth: usize,
+) -> Result<Vec<OsString>, Error> {
+    let mut expander = Expander {
+        syntax,
+        max_depth,
+        stack: Vec::new(),
+        finished: false,
+        out: Vec::with_capacity(args.len()),
+    };
+    for arg in args {
+        expander.arg(arg.clone(), None)?;
+    }
+    Ok(expander.out)
+}
+
+impl Expander {
+    /// Process a single argument. `origin` is the file and line it came from,
+    /// if it didn't come from the command line.
+    fn arg(&mut self, arg: OsString, origin: Option<(&Path, usize)>) -> Result<(), Error> {
+        if self.finished {
+            self.out.push(arg);
+            return Ok(());
+        }
+        if arg == "--" {
+            self.finished = true;
+            self.out.push(arg);
+            return Ok(());
+        }
+        let path = match response_file_path(&arg) {
+            Some(path) => path,
+            None => {
+                self.out.push(arg);
+                return Ok(());
+            }
+        };
+
+        let nesting_error = |message: String| match origin {
+            Some((file, line)) => Error::ResponseFile {
+                path: file.to_owned(),
+                line: Some(line),
+                error: message.into(),
+            },
+            // The nesting limit is zero.
+            None => Error::ResponseFile {
+                path: path.clone(),
+                line: None,
+                error: message.into(),
+            },
+        };
+        if self.stack.len() >= self.max_depth {
+            return Err(nesting_error(
+                "response files are nested too deeply".to_owned(),
+            ));
+        }
+        // Canonicalizing can fail for reasons that reading the file won't,
+        // so fall back to the original path.
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+        if self.stack.contains(&canonical) {
+            return Err(nesting_error(format!(
+                "response file '{}' includes itself",
+                path.display()
+            )));
+        }
+
+        let contents = read(&path).map_err(|err| Error::ResponseFile {
+            path: path.clone(),
+            line: None,
+            error: err.into(),
+        })?;
+        let words = match self.syntax {
+            ResponseFileSyntax::Gnu => split_gnu(&contents),
+            ResponseFileSyntax::Msvc => split_msvc(&contents),
+        };
+        let words = words.map_err(|line| Error::ResponseFile {
+            path: path.clone(),
+            line: Some(line),
+            error: "unterminated quote".into(),
+        })?;
+
+        self.stack.push(canonical);
+        for (word, line) in words {
+            let word = from_bytes(word).map_err(|err| Error::ResponseFile {
+                path: path.clone(),
+                line: Some(line),
+                error: err.into(),
+            })?;
+            self.arg(word, Some((&path, line)))?;
+        }
+        self.stack.pop();
+        Ok(())
+    }
+}
+
+/// Return the path if the argument looks like `@path`.
+fn response_file_path(arg: &OsStr) -> Option<PathBuf> {
+    #[cfg(unix)]
+    {
+        let bytes = arg.as_bytes();
+        if bytes.len() > 1 && bytes[0] == b'@' {
+            return Some(OsStr::from_bytes(&bytes[1..]).into());
+        }
+        None
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStringExt;
+        let units: Vec<u16> = arg.encode_wide().collect();
+        if units.len() > 1 && units[0] == b'@' as u16 {
+            return Some(OsString::from_wide(&units[1..]).into());
+        }
+        None
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let text = arg.to_str()?;
+        if text.len() > 1 && text.starts_with('@') {
+            return Some(text[1..].into());
+        }
+        None
+    }
+}
+
+/// Read a response file into UTF-8-ish bytes.
+///
+/// UTF-16 files are recognized by their byte order mark, as MSVC does.
+fn read(path: &Path) -> io::Result<Vec<u8>> {
+    let bytes = std::fs::read(path)?;
+    if bytes.starts_with(b"\xEF\xBB\xBF") {
+        return Ok(bytes[3..].to_vec());
+    }
+    if bytes.starts_with(b"\xFF\xFE") && bytes.len() % 2 == 0 {
+        let units: Vec<u16> = bytes[2..]
+            .chunks(2)
+            .map(|pair| u16::from(pair[0]) | u16::from(pair[1]) << 8)
+            .collect();
+        return match String::from_utf16(&units) {
+            Ok(text) => Ok(text.into_bytes()),
+            Err(err) => Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+        };
+    }
+    Ok(bytes)
+}
+
+/// Turn a word from a response file into an argument.
+///
+/// On Unix this can't fail. Elsewhere the file must be valid unicode.
+fn from_bytes(bytes: Vec<u8>) -> Result<OsString, std::string::FromUtf8Error> {
+    #[cfg(unix)]
+    {
+        Ok(OsString::from_vec(bytes))
+    }
+    #[cfg(not(unix))]
+    {
+        Ok(String::from_utf8(bytes)?.into())
+    }
+}
+
+fn is_space(byte: u8) -> bool {
+    match byte {
+        b' ' | b'\t' | b'\n' | b'\r' | b'\x0B' | b'\x0C' => true,
+        _ => false,
+    }
+}
+
+/// A word from a response file, with the line number it started on.
+type Word = (Vec<u8>, usize);
+
+/// Split using GNU rules. On failure, return the line of the unterminated quote.
+fn split_gnu(input: &[u8]) -> Result<Vec<Word>, usize> {
+    let mut words = Vec::new();
+    let mut line = 1;
+    let mut bytes = input.iter().cloned();
+    // The current word, if we're inside one.
+    let mut word: Option<Word> = None;
+    // The current quote character and the line it's on.
+    let mut quote: Option<(u8, usize)> = None;
+    while let Some(byte) = bytes.next() {
+        if byte == b'\n' {
+            line += 1;
+        }
+        match (byte, quote) {
+            (b'\\', _) => {
+                let word = word.get_or_insert_with(|| (Vec::new(), line));
+                if let Some(next) = bytes.next() {
+                    if next == b'\n' {
+                        line += 1;
+                    }
+                    word.0.push(next);
+                }
+            }
+            (b'\'', None) | (b'"', None) => {
+                word.get_or_insert_with(|| (Vec::new(), line));
+                quote = Some((byte, line));
+            }
+            (_, Some((open, _))) if byte == open => quote = None,
+            (_, None) if is_space(byte) => words.extend(word.take()),
+            _ => word.get_or_insert_with(|| (Vec::new(), line)).0.push(byte),
+        }
+    }
+    if let Some((_, line)) = quote {
+        return Err(line);
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Split using MSVC rules. On failure, return the line of the unterminated quote.
+fn split_msvc(input: &[u8]) -> Result<Vec<Word>, usize> {
+    let mut words = Vec::new();
+    let mut line = 1;
+    let mut word: Option<Word> = None;
+    let mut quote: Option<usize> = None;
+    let mut backslashes = 0;
+    let mut i = 0;
+    while i < input.len() {
+        let byte = input[i];
+        i += 1;
+        if byte == b'\\' {
+            backslashes += 1;
+            continue;
+        }
+        if backslashes > 0 {
+            let word = &mut word.get_or_insert_with(|| (Vec::new(), line)).0;
+            if byte == b'"' {
+                word.extend(std::iter::repeat(b'\\').take(backslashes / 2));
+                if backslashes % 2 == 1 {
+                    word.push(b'"');
+                    backslashes = 0;
+                    continue;
+                }
+            } else {
+                word.extend(std::iter::repeat(b'\\').take(backslashes));
+            }
+            backslashes = 0;
+        }
+        if byte == b'\n' {
+            line += 1;
+        }
+        match byte {
+            b'"' if quote.is_some() && input.get(i) == Some(&b'"') => {
+                // "" inside quotes is a literal quote
+                i += 1;
+                word.get_or_insert_with(|| (Vec::new(), line)).0.push(b'"');
+            }
+            b'"' if quote.is_some() => quote = None,
+            b'"' => {
+                word.get_or_insert_with(|| (Vec::new(), line));
+                quote = Some(line);
+            }
+            _ if quote.is_none() && is_space(byte) => words.extend(word.take()),
+            _ => word.get_or_insert_with(|| (Vec::new(), line)).0.push(byte),
+        }
+    }
+    if backslashes > 0 {
+        let word = &mut word.get_or_insert_with(|| (Vec::new(), line)).0;
+        word.extend(std::iter::repeat(b'\\').take(backslashes));
+    }
+    if let Some(line) = quote {
+        return Err(line);
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(result: Result<Vec<Word>, usize>) -> Vec<String> {
+        result
+            .unwrap()
+            .into_iter()
+            .map(|(word, _)| String::from_utf8(word).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn gnu() {
+        assert_eq!(
+            words(split_gnu(b"  -a  'b c' \"d\"'e' f\\ g\n\\'h\\\\ ''")),
+            &["-a", "b c", "de", "f g", "'h\\", ""]
+        );
+        assert_eq!(words(split_gnu(b"'a\\'b' \"\\\"\"")), &["a'b", "\""]);
+        assert_eq!(words(split_gnu(b"")), Vec::<String>::new());
+        assert_eq!(split_gnu(b"a\nb\n 'c\n").unwrap_err(), 3);
+        let lines: Vec<_> = split_gnu(b"a\n\nb 'c\nd'\ne")
+            .unwrap()
+            .into_iter()
+            .map(|(_, line)| line)
+            .collect();
+        assert_eq!(lines, &[1, 3, 3, 5]);
+    }
+
+    #[test]
+    fn msvc() {
+        assert_eq!(
+            words(split_msvc(br#"a"b c" \\"d e" \\\"f \\g "h""i" "" x\"#)),
+            &["ab c", "\\d e", "\\\"f", "\\\\g", "h\"i", "", "x\\"]
+        );
+        assert_eq!(
+            words(split_msvc(b"C:\\dir\\ 'a b'")),
+            &["C:\\dir\\", "'a", "b'"]
+        );
+        assert_eq!(split_msvc(b"\n\"a").unwrap_err(), 2);
+    }
+
+    /// Create a scratch directory with the given files.
+    fn scratch(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lexopt-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for &(file, contents) in files {
+            std::fs::write(dir.join(file), contents).unwrap();
+        }
+        dir
+    }
+
+    fn at(dir: &Path, file: &str) -> OsString {
+        let mut arg = OsString::from("@");
+        arg.push(dir.join(file));
+        arg
+    }
+
+    #[test]
+    fn expand_parser() -> Result<(), Error> {
+        use crate::prelude::*;
+
+        let dir = scratch(
+            "expand",
+            &[
+                ("a.rsp", "-x 'one two'\n@INNER\n-- @INNER"),
+                ("b.rsp", "-y\n"),
+                ("empty.rsp", ""),
+            ],
+        );
+        let a = std::fs::read_to_string(dir.join("a.rsp")).unwrap();
+        let inner = dir.join("b.rsp").into_os_string().into_string().unwrap();
+        std::fs::write(dir.join("a.rsp"), a.replace("INNER", &inner)).unwrap();
+
+        let mut p = crate::Parser::from_iter(vec![
+            at(&dir, "bin"),
+            at(&dir, "empty.rsp"),
+            at(&dir, "a.rsp"),
+            "@".into(),
+            "--".into(),
+            at(&dir, "missing.rsp"),
+        ]);
+        p.expand_response_files(ResponseFileSyntax::Gnu, 2)?;
+        assert!(p.bin_name().unwrap().ends_with("bin"));
+        assert_eq!(p.next()?.unwrap(), Short('x'));
+        assert_eq!(p.value()?, "one two");
+        assert_eq!(p.next()?.unwrap(), Short('y'));
+        // Expansion stops at the first "--", even inside a file
+        assert_eq!(p.next()?.unwrap(), Value(format!("@{}", inner).into()));
+        assert_eq!(p.next()?.unwrap(), Value("@".into()));
+        assert_eq!(p.next()?.unwrap(), Value("--".into()));
+        assert_eq!(p.next()?.unwrap(), Value(at(&dir, "missing.rsp")));
+        assert_eq!(p.next()?, None);
+
+        let mut p = crate::Parser::from_args(vec![at(&dir, "a.rsp")]);
+        let err = p
+            .expand_response_files(ResponseFileSyntax::Gnu, 1)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "error in response file '{}' on line 2: response files are nested too deeply",
+                dir.join("a.rsp").display()
+            )
+        );
+        // The parser is left alone
+        assert_eq!(p.next()?.unwrap(), Value(at(&dir, "a.rsp")));
+
+        let mut p = crate::Parser::from_args(vec![at(&dir, "missing.rsp")]);
+        match p.expand_response_files(ResponseFileSyntax::Msvc, 10) {
+            Err(Error::ResponseFile {
+                path, line: None, ..
+            }) => assert_eq!(path, dir.join("missing.rsp")),
+            other => panic!("{:?}", other),
+        }
+
+        let mut p = crate::Parser::from_args(vec![at(&dir, "missing.rsp")]);
+        let err = p
+            .expand_response_files(ResponseFileSyntax::Gnu, 0)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "cannot expand response file '{}': response files are nested too deeply",
+                dir.join("missing.rsp").display()
+            )
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn expand_errors() {
+        let dir = scratch(
+            "errors",
+            &[("self.rsp", "-a\n@self.rsp"), ("quote.rsp", "a\n\"b\nc")],
+        );
+        let err = expand(&[at(&dir, "quote.rsp")], ResponseFileSyntax::Msvc, 10).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "error in response file '{}' on line 2: unterminated quote",
+                dir.join("quote.rsp").display()
+            )
+        );
+
+        // Relative paths are resolved against the current directory, so
+        // spell out the full path.
+        let contents = format!("-a\n{}", at(&dir, "self.rsp").to_str().unwrap());
+        std::fs::write(dir.join("self.rsp"), contents).unwrap();
+        let err = expand(&[at(&dir, "self.rsp")], ResponseFileSyntax::Gnu, 10).unwrap_err();
+        match err {
+            Error::ResponseFile {
+                path,
+                line: Some(2),
+                error,
+            } => {
+                assert_eq!(path, dir.join("self.rsp"));
+                assert!(error.to_string().ends_with("includes itself"));
+            }
+            other => panic!("{:?}", other),
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "must be called before parsing")]
+    fn expand_after_parsing() {
+        let mut p = crate::Parser::from_args(&["-x", "@file"]);
+        p.next().unwrap();
+        let _ = p.expand_response_files(ResponseFileSyntax::Gnu, 10);
+    }
+}
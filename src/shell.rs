@@ -0,0 +1,351 @@
+//! Splitting and quoting arguments the way a POSIX shell does.
+//!
+//! [`split`] turns a string like `-o 'my file'` into separate arguments,
+//! following the quoting rules of `sh` but without any expansions. [`join`]
+//! does the reverse, quoting arguments where necessary.
+//!
+//! Both work on [`OsStr`] without a lossy conversion. On Unix they operate on
+//! the raw bytes and on Windows on UTF-16 code units, so invalid unicode
+//! survives untouched.
+//!
+//! # Example
+//! ```
+//! # fn main() -> Result<(), lexopt::Error> {
+//! use lexopt::prelude::*;
+//!
+//! let args = lexopt::shell::split("-n 10 'my file.txt'")?;
+//! assert_eq!(lexopt::shell::join(&args), "-n 10 'my file.txt'");
+//!
+//! let mut parser = lexopt::Parser::from_args(args);
+//! assert_eq!(parser.next()?, Some(Short('n')));
+//! assert_eq!(parser.value()?.parse::<u32>()?, 10);
+//! assert_eq!(parser.next()?, Some(Value("my file.txt".into())));
+//! # Ok(()) }
+//! ```
+
+use std::ffi::{OsStr, OsString};
+use std::fmt::Display;
+
+#[cfg(unix)]
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+#[cfg(windows)]
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+
+/// A string could not be split because it contains an unterminated quote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitError {
+    quote: char,
+    offset: usize,
+}
+
+impl SplitError {
+    /// The offset of the opening quote.
+    ///
+    /// This counts bytes, or UTF-16 code units on Windows.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl Display for SplitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = if self.quote == '\'' {
+            "single"
+        } else {
+            "double"
+        };
+        write!(f, "unterminated {} quote at offset {}", kind, self.offset)
+    }
+}
+
+impl std::error::Error for SplitError {}
+
+/// Split a string into arguments using POSIX shell quoting rules.
+///
+/// Arguments are separated by spaces, tabs and newlines. Single quotes
+/// preserve everything inside them, double quotes allow escaping `\`, `"`,
+/// `$` and `` ` `` with a backslash, and outside of quotes a backslash
+/// escapes any character. A backslash followed by a newline is removed.
+///
+/// Nothing is expanded: `$HOME`, `~`, `*` and `#` are returned as-is.
+///
+/// # Errors
+///
+/// A [`SplitError`] is returned if a quote is not closed.
+pub fn split<S: AsRef<OsStr> + ?Sized>(text: &S) -> Result<Vec<OsString>, SplitError> {
+    let text = text.as_ref();
+    #[cfg(unix)]
+    {
+        let words = split_units(text.as_bytes())?;
+        Ok(words.into_iter().map(OsString::from_vec).collect())
+    }
+    #[cfg(windows)]
+    {
+        let units: Vec<u16> = text.encode_wide().collect();
+        let words = split_units(&units)?;
+        Ok(words.iter().map(|word| OsString::from_wide(word)).collect())
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let text = text.to_string_lossy();
+        let words = split_units(text.as_bytes())?;
+        Ok(words
+            .into_iter()
+            .map(|word| String::from_utf8(word).unwrap().into())
+            .collect())
+    }
+}
+
+/// Quote an argument for a POSIX shell, if necessary.
+///
+/// Arguments that consist only of ASCII letters, digits, and a few
+/// punctuation characters like `-`, `=` and `/` are returned unchanged.
+/// Anything else is put in single quotes.
+///
+/// # Example
+/// ```
+/// assert_eq!(lexopt::shell::quote("--file"), "--file");
+/// assert_eq!(lexopt::shell::quote("it's"), r"'it'\''s'");
+/// assert_eq!(lexopt::shell::quote(""), "''");
+/// ```
+pub fn quote<S: AsRef<OsStr> + ?Sized>(arg: &S) -> OsString {
+    let arg = arg.as_ref();
+    #[cfg(unix)]
+    {
+        OsString::from_vec(quote_units(arg.as_bytes()))
+    }
+    #[cfg(windows)]
+    {
+        let units: Vec<u16> = arg.encode_wide().collect();
+        OsString::from_wide(&quote_units(&units))
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        quote_str(&arg.to_string_lossy()).into()
+    }
+}
+
+/// Quote arguments for a POSIX shell and join them with spaces.
+///
+/// The result can be turned back into the same arguments with [`split`].
+pub fn join<I>(args: I) -> OsString
+where
+    I: IntoIterator,
+    I::Item: AsRef<OsStr>,
+{
+    let mut joined = OsString::new();
+    for (i, arg) in args.into_iter().enumerate() {
+        if i > 0 {
+            joined.push(" ");
+        }
+        joined.push(quote(&arg));
+    }
+    joined
+}
+
+/// Quote a string for a POSIX shell, if necessary.
+pub(crate) fn quote_str(text: &str) -> String {
+    String::from_utf8(quote_units(text.as_bytes())).unwrap()
+}
+
+pub(crate) fn needs_quotes<T: Copy + Into<u32>>(text: &[T]) -> bool {
+    text.is_empty()
+        || !text.iter().all(|&unit| match unit.into() {
+            0x61..=0x7A | 0x41..=0x5A | 0x30..=0x39 => true,
+            unit => b"-_./=:,+@%".iter().any(|&safe| unit == u32::from(safe)),
+        })
+}
+
+fn quote_units<T: Copy + Into<u32> + From<u8>>(text: &[T]) -> Vec<T> {
+    if !needs_quotes(text) {
+        return text.to_vec();
+    }
+    let mut quoted = vec![T::from(b'\'')];
+    for &unit in text {
+        if unit.into() == u32::from(b'\'') {
+            quoted.extend(b"'\\''".iter().map(|&byte| T::from(byte)));
+        } else {
+            quoted.push(unit);
+        }
+    }
+    quoted.push(T::from(b'\''));
+    quoted
+}
+
+/// Split a string of bytes or UTF-16 code units. All the characters with a
+/// special meaning are ASCII, so everything else is passed through as-is.
+fn split_units<T: Copy + PartialEq + From<u8>>(text: &[T]) -> Result<Vec<Vec<T>>, SplitError> {
+    let is = |unit: T, ch: u8| unit == T::from(ch);
+    let mut words = Vec::new();
+    let mut word = Vec::new();
+    // Whether we're in a word, which may be empty if it's made of quotes.
+    let mut in_word = false;
+    let mut pos = 0;
+    while pos < text.len() {
+        let unit = text[pos];
+        pos += 1;
+        if is(unit, b' ') || is(unit, b'\t') || is(unit, b'\n') {
+            if in_word {
+                words.push(std::mem::replace(&mut word, Vec::new()));
+                in_word = false;
+            }
+            continue;
+        }
+        if is(unit, b'\\') && text.get(pos).map_or(false, |&next| is(next, b'\n')) {
+            // A line continuation. It doesn't start a word, or end one.
+            pos += 1;
+            continue;
+        }
+        in_word = true;
+        if is(unit, b'\\') {
+            match text.get(pos) {
+                Some(&next) => {
+                    word.push(next);
+                    pos += 1;
+                }
+                // sh keeps a trailing backslash.
+                None => word.push(unit),
+            }
+        } else if is(unit, b'\'') {
+            let start = pos - 1;
+            loop {
+                match text.get(pos) {
+                    Some(&next) if is(next, b'\'') => break,
+                    Some(&next) => word.push(next),
+                    None => {
+                        return Err(SplitError {
+                            quote: '\'',
+                            offset: start,
+                        })
+                    }
+                }
+                pos += 1;
+            }
+            pos += 1;
+        } else if is(unit, b'"') {
+            let start = pos - 1;
+            loop {
+                match text.get(pos) {
+                    Some(&next) if is(next, b'"') => break,
+                    Some(&next) if is(next, b'\\') => match text.get(pos + 1) {
+                        Some(&escaped) if is(escaped, b'\n') => pos += 1,
+                        Some(&escaped)
+                            if is(escaped, b'\\')
+                                || is(escaped, b'"')
+                                || is(escaped, b'$')
+                                || is(escaped, b'`') =>
+                        {
+                            word.push(escaped);
+                            pos += 1;
+                        }
+                        _ => word.push(next),
+                    },
+                    Some(&next) => word.push(next),
+                    None => {
+                        return Err(SplitError {
+                            quote: '"',
+                            offset: start,
+                        })
+                    }
+                }
+                pos += 1;
+            }
+            pos += 1;
+        } else {
+            word.push(unit);
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(text: &str, expected: &[&str]) {
+        assert_eq!(split(text).unwrap(), expected, "{:?}", text);
+        let units: Vec<u16> = text.encode_utf16().collect();
+        let words: Vec<String> = split_units(&units)
+            .unwrap()
+            .iter()
+            .map(|word| String::from_utf16(word).unwrap())
+            .collect();
+        assert_eq!(words, expected, "{:?} as UTF-16", text);
+    }
+
+    #[test]
+    fn splitting() {
+        check("", &[]);
+        check("  \t\n ", &[]);
+        check("a b  c", &["a", "b", "c"]);
+        check(" -x --foo=bar ", &["-x", "--foo=bar"]);
+        check("'a b' \"c d\"", &["a b", "c d"]);
+        check("a'b'\"c\"d", &["abcd"]);
+        check("'' \"\" x", &["", "", "x"]);
+        check(r"a\ b \'c \\", &["a b", "'c", "\\"]);
+        check(r"'a\b' '\'\'''", &["a\\b", "\\'"]);
+        check(r#""\a\"\\\$\`""#, &["\\a\"\\$`"]);
+        check("a\\\nb", &["ab"]);
+        check("a \\\n b", &["a", "b"]);
+        check("\\\n", &[]);
+        check("''\\\n", &[""]);
+        check("a ''\\\n", &["a", ""]);
+        check("\"a\\\nb\"", &["ab"]);
+        check("trailing\\", &["trailing\\"]);
+        check("'$HOME' ~ * #", &["$HOME", "~", "*", "#"]);
+        check("ünïcödé 'ü'", &["ünïcödé", "ü"]);
+    }
+
+    #[test]
+    fn quoting() {
+        let args = [
+            "plain",
+            "",
+            "two words",
+            "it's",
+            "'",
+            "''",
+            "a\\b",
+            "$x",
+            "ü",
+            "--x=1,2",
+        ];
+        let joined = join(&args);
+        assert_eq!(
+            joined,
+            r#"plain '' 'two words' 'it'\''s' ''\''' ''\'''\''' 'a\b' '$x' 'ü' --x=1,2"#
+        );
+        assert_eq!(split(&joined).unwrap(), args);
+        assert_eq!(join(&[] as &[&str]), "");
+    }
+
+    #[test]
+    fn split_errors() {
+        let err = split("ab 'cd").unwrap_err();
+        assert_eq!(err.offset(), 3);
+        assert_eq!(err.to_string(), "unterminated single quote at offset 3");
+        let err = split("\"a'\\\"").unwrap_err();
+        assert_eq!(err.offset(), 0);
+        assert_eq!(err.to_string(), "unterminated double quote at offset 0");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn split_invalid() {
+        let words = split(OsStr::from_bytes(b"\xFF '\xFE a' \"\\\xFD\"")).unwrap();
+        assert_eq!(
+            words,
+            &[
+                OsStr::from_bytes(b"\xFF"),
+                OsStr::from_bytes(b"\xFE a"),
+                OsStr::from_bytes(b"\\\xFD"),
+            ]
+        );
+        let quoted = join(&words);
+        assert_eq!(quoted, OsStr::from_bytes(b"'\xFF' '\xFE a' '\\\xFD'"));
+        assert_eq!(split(&quoted).unwrap(), words);
+    }
+}
@@ -0,0 +1,687 @@
+//! Declaring the options of a command, for generating help, man pages and
+//! shell completions.
+//!
+//! lexopt doesn't need to know which options exist. But some things can't
+//! be done without that information, like `--help` output and tab
+//! completion. A [`Command`] describes the options and positional arguments
+//! once, so the help text, the man page, the completion scripts and the
+//! parser can't drift apart: pass each option from [`Parser::next`][crate::Parser::next]
+//! through [`Command::validate`] before matching on it.
+//!
+//! The table is only consulted when you ask for it. The `Parser::next` loop
+//! stays the same.
+//!
+//! This module requires the `spec` feature.
+//!
+//! # Example
+//! ```
+//! # fn main() -> Result<(), lexopt::Error> {
+//! use lexopt::prelude::*;
+//! use lexopt::spec::{Command, Opt, Shell, ValueHint};
+//!
+//! let command = Command::new("hello")
+//!     .option(Opt::new().short('n').long("number").value("NUM").help("Repeat NUM times"))
+//!     .option(Opt::new().long("color").hint(ValueHint::choices(&["auto", "always", "never"])))
+//!     .option(Opt::new().long("completions").value("SHELL").help("Print a completion script"))
+//!     .option(Opt::new().long("help").help("Print help"));
+//!
+//! let mut parser = lexopt::Parser::from_iter(&["hello", "--completions", "fish"]);
+//! while let Some(arg) = parser.next()? {
+//!     command.validate(&arg)?;
+//!     match arg {
+//!         Short('n') | Long("number") => {
+//!             let number: u32 = parser.value()?.parse()?;
+//!         }
+//!         Long("color") => {
+//!             let color = parser.value()?.string()?;
+//!         }
+//!         Long("help") => {
+//!             print!("{}", command.render_help(80));
+//!         }
+//!         Long("completions") => {
+//!             let shell: Shell = parser.value()?.parse()?;
+//!             print!("{}", command.completion(shell));
+//!         }
+//!         _ => return Err(arg.unexpected()),
+//!     }
+//! }
+//! # Ok(()) }
+//! ```
+
+use std::{fmt::Display, str::FromStr};
+
+use crate::{Arg, Error};
+
+mod completion;
+mod help;
+mod man;
+
+/// The options of a command.
+///
+/// This is built up using methods that take and return `self`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Command {
+    name: String,
+    about: String,
+    description: String,
+    version: String,
+    options: Vec<Opt>,
+    positionals: Vec<Positional>,
+    env: Vec<(String, String)>,
+    exit_statuses: Vec<(i32, String)>,
+}
+
+impl Command {
+    /// Start describing the command with this name.
+    ///
+    /// The name is used as the name of the program in completion scripts.
+    pub fn new(name: impl Into<String>) -> Command {
+        Command {
+            name: name.into(),
+            about: String::new(),
+            description: String::new(),
+            version: String::new(),
+            options: Vec::new(),
+            positionals: Vec::new(),
+            env: Vec::new(),
+            exit_statuses: Vec::new(),
+        }
+    }
+
+    /// Set a short description of the command, shown in the help text and
+    /// in the NAME section of the man page.
+    pub fn about(mut self, about: impl Into<String>) -> Command {
+        self.about = about.into();
+        self
+    }
+
+    /// Set a longer description for the DESCRIPTION section of the man page.
+    /// Paragraphs are separated by blank lines.
+    ///
+    /// If this isn't set the short description is used.
+    pub fn description(mut self, description: impl Into<String>) -> Command {
+        self.description = description.into();
+        self
+    }
+
+    /// Set the version, shown in the footer of the man page.
+    pub fn version(mut self, version: impl Into<String>) -> Command {
+        self.version = version.into();
+        self
+    }
+
+    /// Document an environment variable that the command reads, for the
+    /// ENVIRONMENT section of the man page.
+    pub fn env(mut self, name: impl Into<String>, help: impl Into<String>) -> Command {
+        self.env.push((name.into(), help.into()));
+        self
+    }
+
+    /// Document an exit status, for the EXIT STATUS section of the man page.
+    pub fn exit_status(mut self, code: i32, help: impl Into<String>) -> Command {
+        self.exit_statuses.push((code, help.into()));
+        self
+    }
+
+    /// Add an option.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the option has neither a short nor a long name.
+    pub fn option(mut self, option: Opt) -> Command {
+        assert!(
+            option.short.is_some() || option.long.is_some(),
+            "option has no name"
+        );
+        self.options.push(option);
+        self
+    }
+
+    /// Add a positional argument.
+    pub fn positional(mut self, positional: Positional) -> Command {
+        self.positionals.push(positional);
+        self
+    }
+
+    /// The name of the command.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The options, in the order they were added.
+    pub fn options(&self) -> &[Opt] {
+        &self.options
+    }
+
+    /// The positional arguments, in the order they were added.
+    pub fn positionals(&self) -> &[Positional] {
+        &self.positionals
+    }
+
+    /// Find the declared option that matches an argument.
+    ///
+    /// Returns `None` for values and for unknown options.
+    pub fn find(&self, arg: &Arg<'_>) -> Option<&Opt> {
+        self.options.iter().find(|option| match *arg {
+            Arg::Short(ch) => option.short == Some(ch),
+            Arg::Long(name) => option.long.as_ref().map(String::as_str) == Some(name),
+            Arg::Plus(_) | Arg::PlusLong(_) | Arg::Value(_) => false,
+        })
+    }
+
+    /// Check that an argument is a declared option.
+    ///
+    /// Returns the option, or `None` if the argument is a value. For an
+    /// unknown option an [`Error::UnexpectedOption`] is returned, or an
+    /// [`Error::MisspelledOption`] if a known long option is similar.
+    pub fn validate(&self, arg: &Arg<'_>) -> Result<Option<&Opt>, Error> {
+        match *arg {
+            Arg::Value(_) => Ok(None),
+            _ => match self.find(arg) {
+                Some(option) => Ok(Some(option)),
+                None => Err(arg.clone().unexpected_among(&self.long_names())),
+            },
+        }
+    }
+
+    /// The names of the long options, without dashes. These can be passed to
+    /// [`Parser::set_long_options`][crate::Parser::set_long_options] to allow
+    /// abbreviations.
+    pub fn long_names(&self) -> Vec<&str> {
+        self.options
+            .iter()
+            .filter_map(|option| option.long.as_ref().map(String::as_str))
+            .collect()
+    }
+
+    /// The names of the negatable long options, without dashes. These can be
+    /// passed to [`Parser::set_negatable_options`][crate::Parser::set_negatable_options].
+    pub fn negatable_names(&self) -> Vec<&str> {
+        self.options
+            .iter()
+            .filter(|option| option.negatable)
+            .filter_map(|option| option.long.as_ref().map(String::as_str))
+            .collect()
+    }
+
+    /// Render a one-line usage synopsis, like
+    /// `Usage: app [OPTIONS] INPUT [FILE]...`.
+    pub fn usage(&self) -> String {
+        help::usage(self)
+    }
+
+    /// Render GNU-style `--help` output, wrapped to `width` columns.
+    ///
+    /// This contains the usage synopsis, the description, the positional
+    /// arguments that have a description, and the options grouped by
+    /// section.
+    ///
+    /// # Example
+    /// ```
+    /// use lexopt::spec::{Command, Opt, Positional};
+    ///
+    /// let command = Command::new("hello")
+    ///     .about("Greet somebody.")
+    ///     .option(Opt::new().short('n').long("number").value("NUM").default_value("1")
+    ///         .help("How many times to say hello"))
+    ///     .option(Opt::new().long("shout").help("Use capital letters"))
+    ///     .option(Opt::new().short('h').long("help").help("Print help")
+    ///         .section("Miscellaneous"))
+    ///     .positional(Positional::new("THING"));
+    ///
+    /// assert_eq!(command.render_help(60), "\
+    /// Usage: hello [OPTIONS] THING
+    ///
+    /// Greet somebody.
+    ///
+    /// Options:
+    ///   -n, --number=NUM  How many times to say hello [default: 1]
+    ///       --shout       Use capital letters
+    ///
+    /// Miscellaneous:
+    ///   -h, --help        Print help
+    /// ");
+    /// ```
+    pub fn render_help(&self, width: usize) -> String {
+        help::render(self, width)
+    }
+
+    /// Render a man page in roff format, for section 1.
+    ///
+    /// The page has the sections NAME, SYNOPSIS, DESCRIPTION, OPTIONS,
+    /// ENVIRONMENT and EXIT STATUS. Sections without content are left out.
+    ///
+    /// The output only depends on the command, so it doesn't include a
+    /// date. That means it can be checked into a repository and compared in
+    /// tests. It can be viewed with `man -l app.1`.
+    ///
+    /// # Example
+    /// ```
+    /// use lexopt::spec::{Command, Opt};
+    ///
+    /// let command = Command::new("hello")
+    ///     .about("greet somebody")
+    ///     .option(Opt::new().long("shout").help("Use capital letters"))
+    ///     .exit_status(0, "Success.");
+    /// assert!(command.render_man().starts_with(".TH \"HELLO\" 1\n.SH NAME\nhello \\- greet somebody\n"));
+    /// ```
+    pub fn render_man(&self) -> String {
+        man::render(self)
+    }
+
+    /// Generate a completion script for a shell.
+    ///
+    /// See [`Shell`] for how to install each kind of script.
+    ///
+    /// # Panics
+    ///
+    /// Panics for [`Shell::Zsh`] if the name contains whitespace, because
+    /// zsh can't register a completion function for such a name.
+    pub fn completion(&self, shell: Shell) -> String {
+        match shell {
+            Shell::Bash => completion::bash(self),
+            Shell::Zsh => completion::zsh(self),
+            Shell::Fish => completion::fish(self),
+            Shell::PowerShell => completion::powershell(self),
+        }
+    }
+}
+
+/// A declared option.
+///
+/// # Example
+/// ```
+/// use lexopt::spec::{Opt, ValueHint};
+///
+/// let verbose = Opt::new().short('v').long("verbose").help("Print more output");
+/// let output = Opt::new()
+///     .short('o')
+///     .long("output")
+///     .value("FILE")
+///     .hint(ValueHint::File)
+///     .help("Write to FILE");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Opt {
+    short: Option<char>,
+    long: Option<String>,
+    value: Option<String>,
+    value_optional: bool,
+    negatable: bool,
+    hint: ValueHint,
+    help: String,
+    default: Option<String>,
+    section: Option<String>,
+}
+
+impl Opt {
+    /// Start describing an option. It needs at least a short or a long name.
+    pub fn new() -> Opt {
+        Opt::default()
+    }
+
+    /// Set the short name, without the dash.
+    pub fn short(mut self, short: char) -> Opt {
+        self.short = Some(short);
+        self
+    }
+
+    /// Set the long name, without the dashes.
+    pub fn long(mut self, long: impl Into<String>) -> Opt {
+        self.long = Some(long.into());
+        self
+    }
+
+    /// Declare that the option takes a value, with a name like `FILE`.
+    pub fn value(mut self, name: impl Into<String>) -> Opt {
+        self.value = Some(name.into());
+        self
+    }
+
+    /// Declare that the option takes an optional value, like
+    /// `--color[=WHEN]`. The value has to be attached to the option, see
+    /// [`Parser::optional_value`][crate::Parser::optional_value].
+    pub fn optional_value(mut self, name: impl Into<String>) -> Opt {
+        self.value = Some(name.into());
+        self.value_optional = true;
+        self
+    }
+
+    /// Declare that the long option can be negated, like `--no-color` for
+    /// `--color`. It's shown as `--[no-]color` in the help text.
+    ///
+    /// Pass [`Command::negatable_names`] to
+    /// [`Parser::set_negatable_options`][crate::Parser::set_negatable_options]
+    /// to parse the negated form.
+    pub fn negatable(mut self) -> Opt {
+        self.negatable = true;
+        self
+    }
+
+    /// Set the kind of value the option takes, for completion. This implies
+    /// that it takes a value, named `VALUE` if no name was given.
+    pub fn hint(mut self, hint: ValueHint) -> Opt {
+        if self.value.is_none() {
+            self.value = Some("VALUE".to_owned());
+        }
+        self.hint = hint;
+        self
+    }
+
+    /// Set a one-line description.
+    pub fn help(mut self, help: impl Into<String>) -> Opt {
+        self.help = help.into();
+        self
+    }
+
+    /// Set the default value, to show in the help text.
+    pub fn default_value(mut self, default: impl Into<String>) -> Opt {
+        self.default = Some(default.into());
+        self
+    }
+
+    /// Put the option in a section of the help text. Options without a
+    /// section are listed under "Options".
+    pub fn section(mut self, section: impl Into<String>) -> Opt {
+        self.section = Some(section.into());
+        self
+    }
+
+    /// The short name.
+    pub fn get_short(&self) -> Option<char> {
+        self.short
+    }
+
+    /// The long name.
+    pub fn get_long(&self) -> Option<&str> {
+        self.long.as_ref().map(String::as_str)
+    }
+
+    /// The name of the value, if the option takes one.
+    pub fn get_value(&self) -> Option<&str> {
+        self.value.as_ref().map(String::as_str)
+    }
+
+    /// Whether the value is optional, see [`Opt::optional_value`].
+    pub fn is_value_optional(&self) -> bool {
+        self.value_optional
+    }
+
+    /// Whether the long option can be negated, see [`Opt::negatable`].
+    pub fn is_negatable(&self) -> bool {
+        self.negatable
+    }
+
+    /// The kind of value.
+    pub fn get_hint(&self) -> &ValueHint {
+        &self.hint
+    }
+
+    /// The description.
+    pub fn get_help(&self) -> &str {
+        &self.help
+    }
+
+    /// The default value.
+    pub fn get_default_value(&self) -> Option<&str> {
+        self.default.as_ref().map(String::as_str)
+    }
+
+    /// The section of the help text.
+    pub fn get_section(&self) -> Option<&str> {
+        self.section.as_ref().map(String::as_str)
+    }
+
+    /// The names of the option with dashes, short name first.
+    fn names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        if let Some(short) = self.short {
+            names.push(format!("-{}", short));
+        }
+        if let Some(ref long) = self.long {
+            names.push(format!("--{}", long));
+        }
+        names
+    }
+
+    /// The negated long option, like `--no-color`, if there is one.
+    fn negation(&self) -> Option<String> {
+        match self.long {
+            Some(ref long) if self.negatable => Some(format!("--no-{}", long)),
+            _ => None,
+        }
+    }
+}
+
+/// A declared positional argument.
+///
+/// Positional arguments are required by default.
+///
+/// # Example
+/// ```
+/// use lexopt::spec::Positional;
+///
+/// let files = Positional::new("FILE").optional().repeated().help("Files to read");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Positional {
+    name: String,
+    help: String,
+    required: bool,
+    repeated: bool,
+    hint: ValueHint,
+}
+
+impl Positional {
+    /// Start describing a positional argument with a name like `FILE`.
+    pub fn new(name: impl Into<String>) -> Positional {
+        Positional {
+            name: name.into(),
+            help: String::new(),
+            required: true,
+            repeated: false,
+            hint: ValueHint::Any,
+        }
+    }
+
+    /// Set a one-line description.
+    pub fn help(mut self, help: impl Into<String>) -> Positional {
+        self.help = help.into();
+        self
+    }
+
+    /// Mark the argument as optional.
+    pub fn optional(mut self) -> Positional {
+        self.required = false;
+        self
+    }
+
+    /// Allow the argument to be given multiple times.
+    pub fn repeated(mut self) -> Positional {
+        self.repeated = true;
+        self
+    }
+
+    /// Set the kind of value the argument takes, for completion.
+    pub fn hint(mut self, hint: ValueHint) -> Positional {
+        self.hint = hint;
+        self
+    }
+
+    /// The name.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// The description.
+    pub fn get_help(&self) -> &str {
+        &self.help
+    }
+
+    /// Whether the argument is required.
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+
+    /// Whether the argument may be given multiple times.
+    pub fn is_repeated(&self) -> bool {
+        self.repeated
+    }
+
+    /// The kind of value.
+    pub fn get_hint(&self) -> &ValueHint {
+        &self.hint
+    }
+}
+
+/// What kind of value an option takes, for completion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueHint {
+    /// Anything. Nothing is suggested.
+    Any,
+    /// A path to a file.
+    File,
+    /// A path to a directory.
+    Dir,
+    /// One of a fixed list of words.
+    Choices(Vec<String>),
+}
+
+impl ValueHint {
+    /// A convenient way to create [`ValueHint::Choices`].
+    pub fn choices(choices: &[&str]) -> ValueHint {
+        ValueHint::Choices(choices.iter().map(|&choice| choice.to_owned()).collect())
+    }
+}
+
+impl Default for ValueHint {
+    fn default() -> ValueHint {
+        ValueHint::Any
+    }
+}
+
+/// A shell to generate a completion script for.
+///
+/// This can be parsed from `"bash"`, `"zsh"`, `"fish"` and `"powershell"`.
+///
+/// The scripts can be installed like this, for a program called `app`:
+/// - Bash: save as `app` in `~/.local/share/bash-completion/completions`,
+///   or source it from `~/.bashrc`.
+/// - Zsh: save as `_app` in a directory in `$fpath`.
+/// - Fish: save as `app.fish` in `~/.config/fish/completions`.
+/// - PowerShell: source it from `$PROFILE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Shell {
+    /// Bash.
+    Bash,
+    /// Zsh.
+    Zsh,
+    /// Fish.
+    Fish,
+    /// PowerShell.
+    PowerShell,
+}
+
+impl FromStr for Shell {
+    type Err = UnknownShell;
+
+    fn from_str(text: &str) -> Result<Shell, UnknownShell> {
+        match text {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            "powershell" | "pwsh" => Ok(Shell::PowerShell),
+            _ => Err(UnknownShell),
+        }
+    }
+}
+
+/// The error from parsing an unknown [`Shell`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownShell;
+
+impl Display for UnknownShell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unknown shell (expected 'bash', 'zsh', 'fish' or 'powershell')"
+        )
+    }
+}
+
+impl std::error::Error for UnknownShell {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    fn command() -> Command {
+        Command::new("app")
+            .option(Opt::new().short('v').long("verbose"))
+            .option(Opt::new().short('q'))
+            .option(Opt::new().long("version"))
+    }
+
+    #[test]
+    fn validate() {
+        let command = command();
+        assert_eq!(
+            command.validate(&Short('v')).unwrap().unwrap().get_long(),
+            Some("verbose")
+        );
+        assert_eq!(
+            command
+                .validate(&Long("verbose"))
+                .unwrap()
+                .unwrap()
+                .get_short(),
+            Some('v')
+        );
+        assert_eq!(
+            command.validate(&Short('q')).unwrap().unwrap().get_long(),
+            None
+        );
+        assert_eq!(command.validate(&Value("x".into())).unwrap(), None);
+        assert_eq!(
+            command.validate(&Long("verbos")).unwrap_err().to_string(),
+            "invalid option '--verbos'; did you mean '--verbose'?",
+        );
+        assert_eq!(
+            command.validate(&Short('x')).unwrap_err().to_string(),
+            "invalid option '-x'",
+        );
+        assert_eq!(command.long_names(), &["verbose", "version"]);
+        assert!(command.negatable_names().is_empty());
+    }
+
+    #[test]
+    fn negatable() -> Result<(), Error> {
+        let command = command().option(Opt::new().short('c').long("color").negatable());
+        assert_eq!(command.negatable_names(), &["color"]);
+        let mut parser = crate::Parser::from_args(&["--no-color", "--no-verbose"]);
+        parser.set_negatable_options(command.negatable_names());
+        let arg = parser.next()?.unwrap();
+        assert!(command.validate(&arg)?.unwrap().is_negatable());
+        assert!(parser.negated());
+        let arg = parser.next()?.unwrap();
+        assert_eq!(
+            command.validate(&arg).unwrap_err().to_string(),
+            "invalid option '--no-verbose'; did you mean '--verbose'?"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn shells() {
+        assert_eq!("bash".parse(), Ok(Shell::Bash));
+        assert_eq!("pwsh".parse(), Ok(Shell::PowerShell));
+        assert_eq!("sh".parse::<Shell>(), Err(UnknownShell));
+    }
+
+    #[test]
+    #[should_panic]
+    fn nameless_option() {
+        Command::new("app").option(Opt::new().help("nothing"));
+    }
+}
@@ -0,0 +1,532 @@
+//! Completion scripts for [`Command`].
+
+use std::fmt::Write;
+
+use super::{Command, ValueHint};
+use crate::shell;
+
+/// A name that can be used in a shell function name.
+fn ident(name: &str) -> String {
+    name.chars()
+        .map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '_' })
+        .collect()
+}
+
+fn join(words: &[String]) -> String {
+    words.join(" ")
+}
+
+/// Single-quote for PowerShell, which doubles single quotes.
+fn quote_powershell(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// Single-quote for fish, which uses backslash escapes inside quotes.
+fn quote_fish(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// The lines in a bash script that complete a value, if it has a hint.
+fn bash_reply(hint: &ValueHint, indent: &str) -> String {
+    match *hint {
+        ValueHint::Any => String::new(),
+        ValueHint::File => format!("{}COMPREPLY=($(compgen -f -- \"$cur\"))\n", indent),
+        ValueHint::Dir => format!("{}COMPREPLY=($(compgen -d -- \"$cur\"))\n", indent),
+        ValueHint::Choices(ref choices) => {
+            // compgen removes a level of quoting from each word, and the
+            // words it prints are split on IFS, so a choice can contain
+            // spaces as long as it's quoted and the separator is a newline.
+            let words: Vec<String> = choices.iter().map(|c| shell::quote_str(c)).collect();
+            format!(
+                "{}local IFS=$'\\n'\n{}COMPREPLY=($(compgen -W {} -- \"$cur\"))\n",
+                indent,
+                indent,
+                shell::quote_str(&words.join("\n"))
+            )
+        }
+    }
+}
+
+pub(crate) fn bash(command: &Command) -> String {
+    let mut out = String::new();
+    let function = format!("_{}", ident(&command.name));
+    let all: Vec<String> = command
+        .options
+        .iter()
+        .flat_map(|option| option.names().into_iter().chain(option.negation()))
+        .collect();
+
+    let mut value_patterns = Vec::new();
+
+    writeln!(out, "{}() {{", function).unwrap();
+    out.push_str("    local cur=\"${COMP_WORDS[COMP_CWORD]}\"\n");
+    out.push_str("    local prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n");
+    out.push_str("    case \"$prev\" in\n");
+    for option in &command.options {
+        // An optional value has to be attached, so it's never in its own word.
+        if option.value.is_none() || option.value_optional {
+            continue;
+        }
+        let patterns: Vec<String> = option
+            .names()
+            .iter()
+            .map(|name| shell::quote_str(name))
+            .collect();
+        writeln!(out, "        {})", patterns.join("|")).unwrap();
+        out.push_str(&bash_reply(&option.hint, "            "));
+        out.push_str("            return 0\n");
+        out.push_str("            ;;\n");
+        value_patterns.extend(patterns);
+    }
+    out.push_str("    esac\n");
+    out.push_str("    if [[ \"$cur\" == -* ]]; then\n");
+    writeln!(
+        out,
+        "        COMPREPLY=($(compgen -W {} -- \"$cur\"))",
+        shell::quote_str(&join(&all))
+    )
+    .unwrap();
+    out.push_str("        return 0\n");
+    out.push_str("    fi\n");
+    if command
+        .positionals
+        .iter()
+        .any(|positional| positional.hint != ValueHint::Any)
+    {
+        // Count the positional arguments before the cursor, skipping the
+        // values of options.
+        out.push_str("    local i count=0\n");
+        out.push_str("    for ((i = 1; i < COMP_CWORD; i++)); do\n");
+        out.push_str("        case \"${COMP_WORDS[i]}\" in\n");
+        if !value_patterns.is_empty() {
+            writeln!(out, "            {}) ((i++)) ;;", value_patterns.join("|")).unwrap();
+        }
+        out.push_str("            -*) ;;\n");
+        out.push_str("            *) ((count++)) ;;\n");
+        out.push_str("        esac\n");
+        out.push_str("    done\n");
+        out.push_str("    case $count in\n");
+        for (index, positional) in command.positionals.iter().enumerate() {
+            let pattern = if positional.repeated {
+                "*".to_owned()
+            } else {
+                index.to_string()
+            };
+            writeln!(out, "        {})", pattern).unwrap();
+            out.push_str(&bash_reply(&positional.hint, "            "));
+            out.push_str("            ;;\n");
+            // Nothing after a repeated argument can be reached.
+            if positional.repeated {
+                break;
+            }
+        }
+        out.push_str("    esac\n");
+    }
+    out.push_str("}\n");
+    writeln!(
+        out,
+        "complete -o default -F {} {}",
+        function,
+        shell::quote_str(&command.name)
+    )
+    .unwrap();
+    out
+}
+
+/// Escape text for use inside brackets in an `_arguments` spec.
+fn escape_zsh(text: &str) -> String {
+    let mut escaped = String::new();
+    for ch in text.chars() {
+        if let '[' | ']' | ':' | '\\' = ch {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// The action in an `_arguments` spec that completes a value.
+///
+/// `any` is used for [`ValueHint::Any`].
+fn zsh_action(hint: &ValueHint, any: &str) -> String {
+    match *hint {
+        ValueHint::Any => any.to_owned(),
+        ValueHint::File => "_files".to_owned(),
+        ValueHint::Dir => "_files -/".to_owned(),
+        ValueHint::Choices(ref choices) => {
+            let choices: Vec<String> = choices.iter().map(|c| escape_zsh(c)).collect();
+            format!("({})", join(&choices))
+        }
+    }
+}
+
+pub(crate) fn zsh(command: &Command) -> String {
+    let mut out = String::new();
+    let function = format!("_{}", ident(&command.name));
+    let mut specs = Vec::new();
+
+    // compinit splits this line on whitespace and doesn't remove quotes.
+    assert!(
+        !command.name.contains(char::is_whitespace),
+        "can't write a zsh completion script for a name with whitespace: {:?}",
+        command.name
+    );
+    writeln!(out, "#compdef {}", command.name).unwrap();
+    out.push('\n');
+    writeln!(out, "{}() {{", function).unwrap();
+    out.push_str("    _arguments -s -S");
+    for option in &command.options {
+        let names = option.names();
+        let mut forms = Vec::new();
+        if let Some(short) = option.short {
+            forms.push(match option.value {
+                Some(_) if option.value_optional => format!("-{}-", short),
+                Some(_) => format!("-{}+", short),
+                None => format!("-{}", short),
+            });
+        }
+        if let Some(ref long) = option.long {
+            forms.push(match option.value {
+                Some(_) if option.value_optional => format!("--{}=-", long),
+                Some(_) => format!("--{}=", long),
+                None => format!("--{}", long),
+            });
+        }
+        let mut spec = String::new();
+        if !option.help.is_empty() {
+            write!(spec, "[{}]", escape_zsh(&option.help)).unwrap();
+        }
+        if let Some(ref value) = option.value {
+            let action = zsh_action(&option.hint, " ");
+            let colon = if option.value_optional { "::" } else { ":" };
+            write!(spec, "{}{}:{}", colon, escape_zsh(value), action).unwrap();
+        }
+        let spec = shell::quote_str(&spec);
+        if forms.len() == 1 {
+            specs.push(format!("{}{}", shell::quote_str(&forms[0]), spec));
+        } else {
+            specs.push(format!(
+                "{}{{{}}}{}",
+                shell::quote_str(&format!("({})", names.join(" "))),
+                forms.join(","),
+                spec
+            ));
+        }
+        if let Some(negation) = option.negation() {
+            specs.push(shell::quote_str(&negation));
+        }
+    }
+    for positional in &command.positionals {
+        // Without a hint this falls back to the default, like bash's
+        // `complete -o default`.
+        let action = zsh_action(&positional.hint, "_default");
+        let prefix = if positional.repeated {
+            "*:"
+        } else if positional.required {
+            ":"
+        } else {
+            "::"
+        };
+        let spec = format!("{}{}:{}", prefix, escape_zsh(&positional.name), action);
+        specs.push(shell::quote_str(&spec));
+    }
+    for spec in specs {
+        write!(out, " \\\n        {}", spec).unwrap();
+    }
+    out.push_str("\n}\n");
+    out.push('\n');
+    writeln!(out, "if [ \"$funcstack[1]\" = \"{}\" ]; then", function).unwrap();
+    writeln!(out, "    {} \"$@\"", function).unwrap();
+    out.push_str("else\n");
+    writeln!(
+        out,
+        "    compdef {} {}",
+        function,
+        shell::quote_str(&command.name)
+    )
+    .unwrap();
+    out.push_str("fi\n");
+    out
+}
+
+pub(crate) fn fish(command: &Command) -> String {
+    let mut out = String::new();
+    for option in &command.options {
+        write!(out, "complete -c {}", quote_fish(&command.name)).unwrap();
+        if let Some(short) = option.short {
+            write!(out, " -s {}", quote_fish(&short.to_string())).unwrap();
+        }
+        if let Some(ref long) = option.long {
+            write!(out, " -l {}", quote_fish(long)).unwrap();
+        }
+        if option.value.is_some() && !option.value_optional {
+            match option.hint {
+                ValueHint::Any => out.push_str(" -x"),
+                ValueHint::File => out.push_str(" -r -F"),
+                ValueHint::Dir => out.push_str(" -x -a '(__fish_complete_directories)'"),
+                ValueHint::Choices(ref choices) => {
+                    write!(out, " -x -a {}", quote_fish(&join(choices))).unwrap()
+                }
+            }
+        }
+        if !option.help.is_empty() {
+            write!(out, " -d {}", quote_fish(&option.help)).unwrap();
+        }
+        out.push('\n');
+        if let Some(negation) = option.negation() {
+            writeln!(
+                out,
+                "complete -c {} -l {}",
+                quote_fish(&command.name),
+                quote_fish(&negation[2..])
+            )
+            .unwrap();
+        }
+    }
+    out
+}
+
+pub(crate) fn powershell(command: &Command) -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "Register-ArgumentCompleter -Native -CommandName {} -ScriptBlock {{",
+        quote_powershell(&command.name)
+    )
+    .unwrap();
+    out.push_str("    param($wordToComplete, $commandAst, $cursorPosition)\n");
+    out.push_str("    $before = @($commandAst.CommandElements | Where-Object { $_.Extent.EndOffset -lt $cursorPosition })\n");
+    out.push_str("    $prev = if ($before.Count -gt 1) { $before[-1].ToString() } else { '' }\n");
+    out.push_str("    switch -exact ($prev) {\n");
+    for option in &command.options {
+        if option.value.is_none() || option.value_optional {
+            continue;
+        }
+        for name in option.names() {
+            writeln!(out, "        {} {{", quote_powershell(&name)).unwrap();
+            if let ValueHint::Choices(ref choices) = option.hint {
+                let choices: Vec<String> = choices.iter().map(|c| quote_powershell(c)).collect();
+                writeln!(out, "            @({}) |", choices.join(", ")).unwrap();
+                out.push_str("                Where-Object { $_ -like \"$wordToComplete*\" } |\n");
+                out.push_str("                ForEach-Object { [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_) }\n");
+            }
+            // Returning nothing falls back to completing paths.
+            out.push_str("            return\n");
+            out.push_str("        }\n");
+        }
+    }
+    out.push_str("    }\n");
+    out.push_str("    if ($wordToComplete -notlike '-*') {\n");
+    out.push_str("        return\n");
+    out.push_str("    }\n");
+    out.push_str("    @(\n");
+    for option in &command.options {
+        let help = if option.help.is_empty() {
+            option.names().join(", ")
+        } else {
+            option.help.clone()
+        };
+        for name in option.names().into_iter().chain(option.negation()) {
+            writeln!(
+                out,
+                "        ,@({}, {})",
+                quote_powershell(&name),
+                quote_powershell(&help)
+            )
+            .unwrap();
+        }
+    }
+    out.push_str("    ) | Where-Object { $_[0] -like \"$wordToComplete*\" } |\n");
+    out.push_str("        ForEach-Object { [System.Management.Automation.CompletionResult]::new($_[0], $_[0], 'ParameterName', $_[1]) }\n");
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spec::{Opt, Positional};
+
+    fn command() -> Command {
+        Command::new("my-app")
+            .option(Opt::new().short('v').long("verbose").help("Say [more]"))
+            .option(
+                Opt::new()
+                    .short('o')
+                    .long("output")
+                    .value("FILE")
+                    .hint(ValueHint::File)
+                    .help("Write to FILE"),
+            )
+            .option(Opt::new().long("dir").hint(ValueHint::Dir))
+            .option(
+                Opt::new()
+                    .long("color")
+                    .value("WHEN")
+                    .hint(ValueHint::choices(&["auto", "always", "never"]))
+                    .help("It's colorful"),
+            )
+            .option(Opt::new().short('n').value("NUM"))
+    }
+
+    #[test]
+    fn bash() {
+        let script = command().completion(crate::spec::Shell::Bash);
+        assert!(script.starts_with("_my_app() {\n"));
+        assert!(script
+            .contains("        -o|--output)\n            COMPREPLY=($(compgen -f -- \"$cur\"))\n"));
+        assert!(
+            script.contains("        --dir)\n            COMPREPLY=($(compgen -d -- \"$cur\"))\n")
+        );
+        assert!(script.contains(
+            "            local IFS=$'\\n'\n            \
+             COMPREPLY=($(compgen -W 'auto\nalways\nnever' -- \"$cur\"))\n"
+        ));
+        assert!(script.contains("        -n)\n            return 0\n"));
+        assert!(!script.contains("-v|--verbose)"));
+        assert!(
+            script.contains("compgen -W '-v --verbose -o --output --dir --color -n' -- \"$cur\"")
+        );
+        assert!(!script.contains("COMP_WORDS[i]"));
+        assert!(script.ends_with("complete -o default -F _my_app my-app\n"));
+    }
+
+    #[test]
+    fn bash_positionals() {
+        let script = Command::new("app")
+            .option(Opt::new().short('o').value("FILE"))
+            .option(Opt::new().short('q'))
+            .positional(Positional::new("MODE").hint(ValueHint::choices(&["a b", "$c"])))
+            .positional(Positional::new("NAME"))
+            .positional(Positional::new("DIR").repeated().hint(ValueHint::Dir))
+            .positional(Positional::new("UNREACHABLE").hint(ValueHint::File))
+            .completion(crate::spec::Shell::Bash);
+        assert!(script.contains(
+            "    local i count=0\n    for ((i = 1; i < COMP_CWORD; i++)); do\n        \
+             case \"${COMP_WORDS[i]}\" in\n            -o) ((i++)) ;;\n            -*) ;;\n"
+        ));
+        assert!(script.contains(
+            "    case $count in\n        0)\n            local IFS=$'\\n'\n            \
+             COMPREPLY=($(compgen -W ''\\''a b'\\''\n'\\''$c'\\''' -- \"$cur\"))\n            ;;\n        \
+             1)\n            ;;\n        *)\n            COMPREPLY=($(compgen -d -- \"$cur\"))\n            \
+             ;;\n    esac\n}\n"
+        ));
+    }
+
+    #[test]
+    fn zsh() {
+        let script = command().completion(crate::spec::Shell::Zsh);
+        assert!(script.starts_with("#compdef my-app\n\n_my_app() {\n    _arguments -s -S \\\n"));
+        assert!(script.contains("        '(-v --verbose)'{-v,--verbose}'[Say \\[more\\]]' \\\n"));
+        assert!(script
+            .contains("        '(-o --output)'{-o+,--output=}'[Write to FILE]:FILE:_files' \\\n"));
+        assert!(script.contains("        --dir=':VALUE:_files -/' \\\n"));
+        assert!(
+            script.contains("        --color='[It'\\''s colorful]:WHEN:(auto always never)' \\\n")
+        );
+        assert!(script.contains("        -n+':NUM: '\n}\n"));
+        assert!(!script.contains("_default"));
+        assert!(script.contains("    compdef _my_app my-app\n"));
+    }
+
+    #[test]
+    fn zsh_positionals() {
+        let script = Command::new("it's")
+            .positional(Positional::new("MODE").hint(ValueHint::choices(&["a", "b"])))
+            .positional(Positional::new("DIR").optional().hint(ValueHint::Dir))
+            .positional(Positional::new("FILE").optional().repeated())
+            .completion(crate::spec::Shell::Zsh);
+        assert!(script.contains(
+            "    _arguments -s -S \\\n        ':MODE:(a b)' \\\n        '::DIR:_files -/' \\\n        \
+             '*:FILE:_default'\n}\n"
+        ));
+        assert!(script.starts_with("#compdef it's\n"));
+        assert!(script.contains("    compdef _it_s 'it'\\''s'\n"));
+
+        let bash = Command::new("app")
+            .option(Opt::new().long("it's").value("X"))
+            .completion(crate::spec::Shell::Bash);
+        assert!(bash.contains("        '--it'\\''s')\n"));
+    }
+
+    #[test]
+    #[should_panic(expected = "whitespace")]
+    fn zsh_whitespace() {
+        Command::new("my app").completion(crate::spec::Shell::Zsh);
+    }
+
+    #[test]
+    fn fish() {
+        assert_eq!(
+            command().completion(crate::spec::Shell::Fish),
+            "complete -c 'my-app' -s 'v' -l 'verbose' -d 'Say [more]'\n\
+             complete -c 'my-app' -s 'o' -l 'output' -r -F -d 'Write to FILE'\n\
+             complete -c 'my-app' -l 'dir' -x -a '(__fish_complete_directories)'\n\
+             complete -c 'my-app' -l 'color' -x -a 'auto always never' -d 'It\\'s colorful'\n\
+             complete -c 'my-app' -s 'n' -x\n"
+        );
+    }
+
+    #[test]
+    fn optional_values() {
+        let command = Command::new("app").option(
+            Opt::new()
+                .short('c')
+                .long("color")
+                .optional_value("WHEN")
+                .hint(ValueHint::choices(&["auto", "never"])),
+        );
+        let bash = command.completion(crate::spec::Shell::Bash);
+        assert!(!bash.contains("-c|--color)"));
+        assert!(bash.contains("compgen -W '-c --color' -- \"$cur\""));
+        assert!(command
+            .completion(crate::spec::Shell::Zsh)
+            .contains("        '(-c --color)'{-c-,--color=-}'::WHEN:(auto never)'\n}\n"));
+        assert_eq!(
+            command.completion(crate::spec::Shell::Fish),
+            "complete -c 'app' -s 'c' -l 'color'\n"
+        );
+        assert!(!command
+            .completion(crate::spec::Shell::PowerShell)
+            .contains("'--color' {"));
+    }
+
+    #[test]
+    fn negatable() {
+        let command = Command::new("app").option(
+            Opt::new()
+                .short('c')
+                .long("color")
+                .negatable()
+                .help("Use color"),
+        );
+        assert!(command
+            .completion(crate::spec::Shell::Bash)
+            .contains("compgen -W '-c --color --no-color' -- \"$cur\""));
+        assert!(command.completion(crate::spec::Shell::Zsh).contains(
+            "        '(-c --color)'{-c,--color}'[Use color]' \\\n        --no-color\n}\n"
+        ));
+        assert_eq!(
+            command.completion(crate::spec::Shell::Fish),
+            "complete -c 'app' -s 'c' -l 'color' -d 'Use color'\n\
+             complete -c 'app' -l 'no-color'\n"
+        );
+        assert!(command
+            .completion(crate::spec::Shell::PowerShell)
+            .contains("        ,@('--no-color', 'Use color')\n"));
+    }
+
+    #[test]
+    fn powershell() {
+        let script = command().completion(crate::spec::Shell::PowerShell);
+        assert!(script.starts_with("Register-ArgumentCompleter -Native -CommandName 'my-app' "));
+        assert!(
+            script.contains("        '--color' {\n            @('auto', 'always', 'never') |\n")
+        );
+        assert!(script.contains("        '-n' {\n            return\n        }\n"));
+        assert!(!script.contains("'-v' {"));
+        assert!(script.contains("        ,@('--color', 'It''s colorful')\n"));
+        assert!(script.contains("        ,@('-v', 'Say [more]')\n"));
+        assert!(script.contains("        ,@('--dir', '--dir')\n"));
+    }
+}
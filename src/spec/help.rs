@@ -0,0 +1,336 @@
+//! `--help` output for [`Command`].
+
+use super::{Command, Opt, Positional};
+
+/// Labels longer than this don't push the descriptions of the other rows
+/// further right. Their description starts on the next line instead.
+const MAX_LABEL: usize = 28;
+
+/// Descriptions are wrapped to at least this many columns, even if that
+/// makes lines longer than the requested width.
+const MIN_HELP_WIDTH: usize = 20;
+
+pub(crate) fn usage(command: &Command) -> String {
+    let mut usage = format!("Usage: {}", command.name);
+    if !command.options.is_empty() {
+        usage.push_str(" [OPTIONS]");
+    }
+    for positional in &command.positionals {
+        usage.push(' ');
+        usage.push_str(&synopsis(positional));
+    }
+    usage
+}
+
+fn synopsis(positional: &Positional) -> String {
+    let mut synopsis = if positional.required {
+        positional.name.clone()
+    } else {
+        format!("[{}]", positional.name)
+    };
+    if positional.repeated {
+        synopsis.push_str("...");
+    }
+    synopsis
+}
+
+fn option_label(option: &Opt) -> String {
+    let value = option.value.as_ref();
+    let optional = option.value_optional;
+    let long = option.long.as_ref().map(|long| {
+        if option.negatable {
+            format!("[no-]{}", long)
+        } else {
+            long.clone()
+        }
+    });
+    match (option.short, long) {
+        (Some(short), Some(long)) => match value {
+            Some(value) if optional => format!("-{}, --{}[={}]", short, long, value),
+            Some(value) => format!("-{}, --{}={}", short, long, value),
+            None => format!("-{}, --{}", short, long),
+        },
+        (Some(short), None) => match value {
+            Some(value) if optional => format!("-{}[{}]", short, value),
+            Some(value) => format!("-{} {}", short, value),
+            None => format!("-{}", short),
+        },
+        (None, Some(long)) => match value {
+            Some(value) if optional => format!("    --{}[={}]", long, value),
+            Some(value) => format!("    --{}={}", long, value),
+            None => format!("    --{}", long),
+        },
+        (None, None) => unreachable!("option has no name"),
+    }
+}
+
+fn option_help(option: &Opt) -> String {
+    match option.default {
+        Some(ref default) if option.help.is_empty() => format!("[default: {}]", default),
+        Some(ref default) => format!("{} [default: {}]", option.help, default),
+        None => option.help.clone(),
+    }
+}
+
+/// A section of the help text, with rows of labels and descriptions.
+struct Section {
+    title: String,
+    rows: Vec<(String, String)>,
+}
+
+pub(crate) fn render(command: &Command, width: usize) -> String {
+    let mut sections = Vec::new();
+
+    let arguments: Vec<(String, String)> = command
+        .positionals
+        .iter()
+        .filter(|positional| !positional.help.is_empty())
+        .map(|positional| (positional.name.clone(), positional.help.clone()))
+        .collect();
+    if !arguments.is_empty() {
+        sections.push(Section {
+            title: "Arguments".to_owned(),
+            rows: arguments,
+        });
+    }
+
+    let first_option = sections.len();
+    for option in &command.options {
+        let title = option.section.as_ref().map_or("Options", String::as_str);
+        let row = (option_label(option), option_help(option));
+        let index = sections[first_option..]
+            .iter()
+            .position(|section| section.title == title);
+        match index {
+            Some(index) => sections[first_option + index].rows.push(row),
+            None => sections.push(Section {
+                title: title.to_owned(),
+                rows: vec![row],
+            }),
+        }
+    }
+
+    let column = sections
+        .iter()
+        .flat_map(|section| section.rows.iter())
+        .map(|row| row.0.chars().count())
+        .filter(|&len| len <= MAX_LABEL)
+        .max()
+        .unwrap_or(0)
+        + 4;
+    let help_width = width.saturating_sub(column).max(MIN_HELP_WIDTH);
+
+    let mut out = usage(command);
+    out.push('\n');
+    if !command.about.is_empty() {
+        out.push('\n');
+        for line in wrap(&command.about, width.max(MIN_HELP_WIDTH)) {
+            out.push_str(&line);
+            out.push('\n');
+        }
+    }
+    for section in &sections {
+        out.push('\n');
+        out.push_str(&section.title);
+        out.push_str(":\n");
+        for (label, help) in &section.rows {
+            let mut line = format!("  {}", label);
+            let mut lines = wrap(help, help_width).into_iter();
+            if let Some(first) = lines.next() {
+                let len = line.chars().count();
+                if len + 2 > column {
+                    out.push_str(&line);
+                    out.push('\n');
+                    line.clear();
+                }
+                let pad = column - line.chars().count();
+                line.extend(std::iter::repeat(' ').take(pad));
+                line.push_str(&first);
+            }
+            out.push_str(&line);
+            out.push('\n');
+            for rest in lines {
+                out.extend(std::iter::repeat(' ').take(column));
+                out.push_str(&rest);
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+/// Wrap text into lines of at most `width` columns. Words that are too long
+/// get a line of their own.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut len = 0;
+    for word in text.split_whitespace() {
+        let word_len = word.chars().count();
+        if len > 0 && len + 1 + word_len > width {
+            lines.push(std::mem::replace(&mut line, String::new()));
+            len = 0;
+        }
+        if len > 0 {
+            line.push(' ');
+            len += 1;
+        }
+        line.push_str(word);
+        len += word_len;
+    }
+    if len > 0 {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command() -> Command {
+        Command::new("app")
+            .about("Do things with files. This description is long enough to be wrapped.")
+            .positional(Positional::new("INPUT"))
+            .positional(
+                Positional::new("FILE")
+                    .optional()
+                    .repeated()
+                    .help("Extra files"),
+            )
+            .option(Opt::new().short('v').long("verbose").help("Say more"))
+            .option(Opt::new().short('j').value("N").default_value("1"))
+            .option(
+                Opt::new()
+                    .long("output")
+                    .value("FILE")
+                    .help("Write the result to FILE instead of standard output"),
+            )
+            .option(
+                Opt::new()
+                    .long("a-very-long-option-name")
+                    .value("VALUE")
+                    .help("Long")
+                    .section("Advanced"),
+            )
+            .option(Opt::new().short('q').section("Advanced"))
+    }
+
+    #[test]
+    fn usage() {
+        assert_eq!(command().usage(), "Usage: app [OPTIONS] INPUT [FILE]...");
+        assert_eq!(Command::new("x").usage(), "Usage: x");
+        assert_eq!(
+            Command::new("x")
+                .positional(Positional::new("A").repeated())
+                .usage(),
+            "Usage: x A..."
+        );
+    }
+
+    #[test]
+    fn render() {
+        assert_eq!(
+            command().render_help(50),
+            "\
+Usage: app [OPTIONS] INPUT [FILE]...
+
+Do things with files. This description is long
+enough to be wrapped.
+
+Arguments:
+  FILE               Extra files
+
+Options:
+  -v, --verbose      Say more
+  -j N               [default: 1]
+      --output=FILE  Write the result to FILE
+                     instead of standard output
+
+Advanced:
+      --a-very-long-option-name=VALUE
+                     Long
+  -q
+"
+        );
+        assert_eq!(Command::new("x").render_help(80), "Usage: x\n");
+    }
+
+    #[test]
+    fn optional_values() {
+        let help = Command::new("x")
+            .option(Opt::new().short('c').long("color").optional_value("WHEN"))
+            .option(Opt::new().long("color2").optional_value("WHEN"))
+            .option(Opt::new().short('d').optional_value("N"))
+            .render_help(80);
+        assert_eq!(
+            help,
+            "\
+Usage: x [OPTIONS]
+
+Options:
+  -c, --color[=WHEN]
+      --color2[=WHEN]
+  -d[N]
+"
+        );
+    }
+
+    #[test]
+    fn negatable() {
+        let command = Command::new("x")
+            .option(
+                Opt::new()
+                    .short('c')
+                    .long("color")
+                    .negatable()
+                    .help("Use color"),
+            )
+            .option(Opt::new().long("pager").negatable())
+            .option(Opt::new().short('q').negatable());
+        assert_eq!(
+            command.render_help(80),
+            "\
+Usage: x [OPTIONS]
+
+Options:
+  -c, --[no-]color  Use color
+      --[no-]pager
+  -q
+"
+        );
+        assert!(command
+            .render_man()
+            .contains(".TP\n\\fB\\-c\\fR, \\fB\\-\\-[no\\-]color\\fR\nUse color\n"));
+    }
+
+    #[test]
+    fn narrow() {
+        let help = Command::new("x")
+            .option(
+                Opt::new()
+                    .short('x')
+                    .help("one two three four five six seven"),
+            )
+            .render_help(10);
+        assert_eq!(
+            help,
+            "\
+Usage: x [OPTIONS]
+
+Options:
+  -x  one two three four
+      five six seven
+"
+        );
+    }
+
+    #[test]
+    fn wrapping() {
+        assert_eq!(wrap("", 10), Vec::<String>::new());
+        assert_eq!(wrap("  a  b ", 10), &["a b"]);
+        assert_eq!(wrap("aaa bbb ccc", 7), &["aaa bbb", "ccc"]);
+        assert_eq!(wrap("a verylongword b", 4), &["a", "verylongword", "b"]);
+        assert_eq!(wrap("ü ü ü", 3), &["ü ü", "ü"]);
+    }
+}
@@ -0,0 +1,330 @@
+//! Man pages for [`Command`].
+
+use std::fmt::Write;
+
+use super::{Command, Opt, Positional};
+
+/// Escape text for roff. A line that starts with `.` or `'` would be a
+/// request, so that's escaped as well.
+fn escape(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e");
+    if escaped.starts_with('.') || escaped.starts_with('\'') {
+        format!("\\&{}", escaped)
+    } else {
+        escaped
+    }
+}
+
+/// Escape an option or argument name. Dashes become real minus signs so
+/// that they can be copied and searched for.
+fn escape_name(name: &str) -> String {
+    escape(name).replace('-', "\\-")
+}
+
+fn bold(text: &str) -> String {
+    format!("\\fB{}\\fR", escape_name(text))
+}
+
+fn italic(text: &str) -> String {
+    format!("\\fI{}\\fR", escape_name(text))
+}
+
+/// Join the words of a paragraph into a single line.
+fn paragraph(text: &str) -> String {
+    escape(&text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+fn synopsis(positional: &Positional) -> String {
+    let mut synopsis = italic(&positional.name);
+    if !positional.required {
+        synopsis = format!("[{}]", synopsis);
+    }
+    if positional.repeated {
+        synopsis.push_str("...");
+    }
+    synopsis
+}
+
+fn option_label(option: &Opt) -> String {
+    let mut names = Vec::new();
+    if let Some(short) = option.short {
+        names.push(bold(&format!("-{}", short)));
+    }
+    let mut label = match option.long {
+        Some(ref long) => {
+            if option.negatable {
+                names.push(bold(&format!("--[no-]{}", long)));
+            } else {
+                names.push(bold(&format!("--{}", long)));
+            }
+            let mut label = names.join(", ");
+            match option.value {
+                Some(ref value) if option.value_optional => {
+                    write!(label, "[={}]", italic(value)).unwrap()
+                }
+                Some(ref value) => write!(label, "={}", italic(value)).unwrap(),
+                None => (),
+            }
+            label
+        }
+        None => {
+            let mut label = names.join(", ");
+            match option.value {
+                Some(ref value) if option.value_optional => {
+                    write!(label, "[{}]", italic(value)).unwrap()
+                }
+                Some(ref value) => write!(label, " {}", italic(value)).unwrap(),
+                None => (),
+            }
+            label
+        }
+    };
+    if label.starts_with('.') {
+        label.insert_str(0, "\\&");
+    }
+    label
+}
+
+/// The section an option is listed in.
+fn section(option: &Opt) -> &str {
+    option.section.as_ref().map_or("Options", String::as_str)
+}
+
+/// Escape text for use as a quoted macro argument.
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", escape(text).replace('"', "\\(dq"))
+}
+
+pub(crate) fn render(command: &Command) -> String {
+    let mut out = String::new();
+
+    let title = quoted(&command.name.to_uppercase());
+    if command.version.is_empty() {
+        writeln!(out, ".TH {} 1", title).unwrap();
+    } else {
+        writeln!(
+            out,
+            ".TH {} 1 \"\" {}",
+            title,
+            quoted(&format!("{} {}", command.name, command.version))
+        )
+        .unwrap();
+    }
+
+    out.push_str(".SH NAME\n");
+    if command.about.is_empty() {
+        writeln!(out, "{}", escape_name(&command.name)).unwrap();
+    } else {
+        writeln!(
+            out,
+            "{} \\- {}",
+            escape_name(&command.name),
+            paragraph(&command.about)
+        )
+        .unwrap();
+    }
+
+    out.push_str(".SH SYNOPSIS\n");
+    writeln!(out, ".B {}", escape(&command.name)).unwrap();
+    let mut synopsis = Vec::new();
+    if !command.options.is_empty() {
+        synopsis.push("[\\fIOPTIONS\\fR]".to_owned());
+    }
+    synopsis.extend(command.positionals.iter().map(self::synopsis));
+    if !synopsis.is_empty() {
+        writeln!(out, "{}", synopsis.join(" ")).unwrap();
+    }
+
+    let description = if command.description.is_empty() {
+        &command.about
+    } else {
+        &command.description
+    };
+    let paragraphs: Vec<&str> = description
+        .split("\n\n")
+        .filter(|paragraph| !paragraph.trim().is_empty())
+        .collect();
+    let arguments: Vec<&Positional> = command
+        .positionals
+        .iter()
+        .filter(|positional| !positional.help.is_empty())
+        .collect();
+    if !paragraphs.is_empty() || !arguments.is_empty() {
+        out.push_str(".SH DESCRIPTION\n");
+        for (i, text) in paragraphs.iter().enumerate() {
+            if i > 0 {
+                out.push_str(".PP\n");
+            }
+            writeln!(out, "{}", paragraph(text)).unwrap();
+        }
+        for positional in arguments {
+            out.push_str(".TP\n");
+            writeln!(out, "{}", italic(&positional.name)).unwrap();
+            writeln!(out, "{}", paragraph(&positional.help)).unwrap();
+        }
+    }
+
+    if !command.options.is_empty() {
+        out.push_str(".SH OPTIONS\n");
+        // Sections are in order of first appearance, like in the help text,
+        // and options without one are in "Options".
+        let mut sections: Vec<&str> = Vec::new();
+        for option in &command.options {
+            let section = section(option);
+            if !sections.contains(&section) {
+                sections.push(section);
+            }
+        }
+        for (i, &title) in sections.iter().enumerate() {
+            // The first one is already under the OPTIONS heading.
+            if i > 0 || title != "Options" {
+                writeln!(out, ".SS {}", escape(title)).unwrap();
+            }
+            for option in &command.options {
+                if section(option) != title {
+                    continue;
+                }
+                out.push_str(".TP\n");
+                writeln!(out, "{}", option_label(option)).unwrap();
+                let mut help = paragraph(&option.help);
+                if let Some(ref default) = option.default {
+                    if !help.is_empty() {
+                        help.push(' ');
+                    }
+                    write!(help, "[default: {}]", escape(default)).unwrap();
+                }
+                if !help.is_empty() {
+                    writeln!(out, "{}", help).unwrap();
+                }
+            }
+        }
+    }
+
+    if !command.env.is_empty() {
+        out.push_str(".SH ENVIRONMENT\n");
+        for (name, help) in &command.env {
+            out.push_str(".TP\n");
+            writeln!(out, "{}", bold(name)).unwrap();
+            writeln!(out, "{}", paragraph(help)).unwrap();
+        }
+    }
+
+    if !command.exit_statuses.is_empty() {
+        out.push_str(".SH EXIT STATUS\n");
+        for &(code, ref help) in &command.exit_statuses {
+            out.push_str(".TP\n");
+            writeln!(out, "{}", bold(&code.to_string())).unwrap();
+            writeln!(out, "{}", paragraph(help)).unwrap();
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let command = Command::new("my-app")
+            .about("do things")
+            .description("First paragraph,\nstill first.\n\n.Second paragraph.")
+            .version("1.0 \"beta\"")
+            .positional(Positional::new("INPUT").help("The input"))
+            .positional(Positional::new("FILE").optional().repeated())
+            .option(Opt::new().short('v').long("verbose").help("Say more"))
+            .option(Opt::new().short('j').value("N").default_value("1"))
+            .option(
+                Opt::new()
+                    .long("path")
+                    .value("DIR")
+                    .help(r"Like C:\dir")
+                    .section("Advanced"),
+            )
+            .option(Opt::new().short('q').help("Quiet"))
+            .option(Opt::new().long("color").optional_value("WHEN"))
+            .env("MY_APP_DIR", "Where to look")
+            .exit_status(0, "Success")
+            .exit_status(2, "Bad arguments");
+        assert_eq!(
+            command.render_man(),
+            r#".TH "MY-APP" 1 "" "my-app 1.0 \(dqbeta\(dq"
+.SH NAME
+my\-app \- do things
+.SH SYNOPSIS
+.B my-app
+[\fIOPTIONS\fR] \fIINPUT\fR [\fIFILE\fR]...
+.SH DESCRIPTION
+First paragraph, still first.
+.PP
+\&.Second paragraph.
+.TP
+\fIINPUT\fR
+The input
+.SH OPTIONS
+.TP
+\fB\-v\fR, \fB\-\-verbose\fR
+Say more
+.TP
+\fB\-j\fR \fIN\fR
+[default: 1]
+.TP
+\fB\-q\fR
+Quiet
+.TP
+\fB\-\-color\fR[=\fIWHEN\fR]
+.SS Advanced
+.TP
+\fB\-\-path\fR=\fIDIR\fR
+Like C:\edir
+.SH ENVIRONMENT
+.TP
+\fBMY_APP_DIR\fR
+Where to look
+.SH EXIT STATUS
+.TP
+\fB0\fR
+Success
+.TP
+\fB2\fR
+Bad arguments
+"#
+        );
+    }
+
+    #[test]
+    fn minimal() {
+        assert_eq!(
+            Command::new("x").render_man(),
+            ".TH \"X\" 1\n.SH NAME\nx\n.SH SYNOPSIS\n.B x\n"
+        );
+    }
+
+    #[test]
+    fn sections() {
+        let command = Command::new("my app")
+            .option(Opt::new().short('a').section("First"))
+            .option(Opt::new().short('b'))
+            .option(Opt::new().short('c').section("First"));
+        assert_eq!(
+            command.render_man(),
+            r#".TH "MY APP" 1
+.SH NAME
+my app
+.SH SYNOPSIS
+.B my app
+[\fIOPTIONS\fR]
+.SH OPTIONS
+.SS First
+.TP
+\fB\-a\fR
+.TP
+\fB\-c\fR
+.SS Options
+.TP
+\fB\-b\fR
+"#
+        );
+    }
+}
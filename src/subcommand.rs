@@ -0,0 +1,344 @@
+//! Dispatching to subcommands.
+
+use std::ffi::OsString;
+use std::fmt;
+
+use crate::{Error, Parser};
+
+type Handler<'a, T> = Box<dyn FnMut(&mut Parser) -> Result<T, Error> + 'a>;
+
+struct Entry<'a, T> {
+    name: String,
+    aliases: Vec<String>,
+    handler: Handler<'a, T>,
+}
+
+impl<T> Entry<'_, T> {
+    fn names(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.name.as_str()).chain(self.aliases.iter().map(String::as_str))
+    }
+}
+
+/// A table of subcommands, for programs like `git` and `cargo`.
+///
+/// Each subcommand has a handler that receives the [`Parser`] and parses
+/// the rest of the arguments. When you find a positional argument that should
+/// name a subcommand, pass it to [`dispatch`][Subcommands::dispatch].
+///
+/// The parser remembers which subcommands it was passed to, see
+/// [`Parser::subcommands`] and [`Parser::command_name`]. A handler can
+/// dispatch to nested subcommands with its own `Subcommands`.
+///
+/// # Example
+/// ```
+/// # fn main() -> Result<(), lexopt::Error> {
+/// use lexopt::prelude::*;
+///
+/// fn install(parser: &mut lexopt::Parser) -> Result<String, lexopt::Error> {
+///     let mut package = None;
+///     while let Some(arg) = parser.next()? {
+///         match arg {
+///             Value(val) if package.is_none() => package = Some(val.string()?),
+///             _ => return Err(arg.unexpected()),
+///         }
+///     }
+///     Ok(format!("installing {}", package.ok_or("missing CRATE argument")?))
+/// }
+///
+/// let mut parser = lexopt::Parser::from_iter(&["cargo", "--verbose", "inst", "ripgrep"]);
+/// let mut verbose = false;
+/// while let Some(arg) = parser.next()? {
+///     match arg {
+///         Long("verbose") => verbose = true,
+///         Value(name) => {
+///             let mut subcommands = lexopt::Subcommands::new();
+///             subcommands.add("build", &["b"], |_parser| Ok("building".to_owned()));
+///             subcommands.add("install", &[], install);
+///             subcommands.set_abbreviations(true);
+///
+///             let result = subcommands.dispatch(name, &mut parser)?;
+///             assert_eq!(result, "installing ripgrep");
+///             assert_eq!(parser.command_name().unwrap(), "cargo install");
+///             break;
+///         }
+///         _ => return Err(arg.unexpected()),
+///     }
+/// }
+/// # Ok(()) }
+/// ```
+pub struct Subcommands<'a, T> {
+    entries: Vec<Entry<'a, T>>,
+    abbreviations: bool,
+}
+
+impl<'a, T> Subcommands<'a, T> {
+    /// Create an empty table.
+    pub fn new() -> Subcommands<'a, T> {
+        Subcommands {
+            entries: Vec::new(),
+            abbreviations: false,
+        }
+    }
+
+    /// Register a subcommand, with alternative names and a handler.
+    ///
+    /// The handler is called with the parser positioned right after the
+    /// subcommand's name.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name or one of the aliases is already in use.
+    pub fn add<F>(&mut self, name: &str, aliases: &[&str], handler: F)
+    where
+        F: FnMut(&mut Parser) -> Result<T, Error> + 'a,
+    {
+        for new in std::iter::once(&name).chain(aliases) {
+            assert!(
+                !self
+                    .entries
+                    .iter()
+                    .any(|entry| entry.names().any(|n| n == *new)),
+                "subcommand {:?} was registered twice",
+                new
+            );
+        }
+        self.entries.push(Entry {
+            name: name.to_owned(),
+            aliases: aliases.iter().map(|&alias| alias.to_owned()).collect(),
+            handler: Box::new(handler),
+        });
+    }
+
+    /// Allow unambiguous abbreviations of subcommands, like `inst` for
+    /// `install`. This is off by default.
+    ///
+    /// An abbreviation that matches several subcommands is reported as
+    /// [`Error::AmbiguousSubcommand`]. An exact match always wins, even if
+    /// it's also the prefix of another subcommand.
+    pub fn set_abbreviations(&mut self, abbreviations: bool) {
+        self.abbreviations = abbreviations;
+    }
+
+    /// Run the handler for the subcommand named by `name`, usually a value
+    /// just returned by [`Parser::next`].
+    ///
+    /// The canonical name of the subcommand is added to
+    /// [`Parser::subcommands`] before the handler runs, and stays there
+    /// afterwards so that errors can be reported with the full command name.
+    ///
+    /// If `name` doesn't match any subcommand this returns
+    /// [`Error::UnknownSubcommand`], which lists the valid subcommands.
+    pub fn dispatch(&mut self, name: OsString, parser: &mut Parser) -> Result<T, Error> {
+        let index = self
+            .find(&name)
+            .map_err(|err| err.with_position(parser.current_position()))?;
+        let entry = &mut self.entries[index];
+        parser.subcommands.push(entry.name.clone());
+        (entry.handler)(parser)
+    }
+
+    fn find(&self, name: &OsString) -> Result<usize, Error> {
+        let unknown = || Error::UnknownSubcommand {
+            subcommand: name.clone(),
+            known: self
+                .entries
+                .iter()
+                .map(|entry| entry.name.clone())
+                .collect(),
+            position: None,
+        };
+        let name = match name.to_str() {
+            Some(name) => name,
+            None => return Err(unknown()),
+        };
+        if let Some(index) = self
+            .entries
+            .iter()
+            .position(|entry| entry.names().any(|known| known == name))
+        {
+            return Ok(index);
+        }
+        if !self.abbreviations || name.is_empty() {
+            return Err(unknown());
+        }
+        let candidates: Vec<usize> = (0..self.entries.len())
+            .filter(|&index| {
+                self.entries[index]
+                    .names()
+                    .any(|known| known.starts_with(name))
+            })
+            .collect();
+        match candidates.len() {
+            0 => Err(unknown()),
+            1 => Ok(candidates[0]),
+            _ => Err(Error::AmbiguousSubcommand {
+                subcommand: name.to_owned(),
+                candidates: candidates
+                    .into_iter()
+                    .map(|index| self.entries[index].name.clone())
+                    .collect(),
+                position: None,
+            }),
+        }
+    }
+}
+
+impl<T> Default for Subcommands<'_, T> {
+    fn default() -> Self {
+        Subcommands::new()
+    }
+}
+
+impl<T> fmt::Debug for Subcommands<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subcommands")
+            .field(
+                "names",
+                &self
+                    .entries
+                    .iter()
+                    .map(|entry| entry.names().collect())
+                    .collect::<Vec<Vec<&str>>>(),
+            )
+            .field("abbreviations", &self.abbreviations)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use crate::Position;
+
+    fn subcommands<'a>() -> Subcommands<'a, String> {
+        let mut subcommands = Subcommands::new();
+        subcommands.add("install", &["i"], |parser: &mut Parser| {
+            let package = parser.value()?.string()?;
+            Ok(format!("install {}", package))
+        });
+        subcommands.add("init", &[], |_: &mut Parser| Ok("init".to_owned()));
+        subcommands.add("build", &["b", "make"], |_: &mut Parser| {
+            Ok("build".to_owned())
+        });
+        subcommands
+    }
+
+    fn dispatch(subcommands: &mut Subcommands<'_, String>, args: &[&str]) -> Result<String, Error> {
+        let mut parser = Parser::from_iter(args);
+        while let Some(arg) = parser.next()? {
+            if let Value(name) = arg {
+                return subcommands.dispatch(name, &mut parser);
+            }
+        }
+        panic!("expected a value")
+    }
+
+    #[test]
+    fn dispatching() {
+        let mut subcommands = subcommands();
+        assert_eq!(
+            dispatch(&mut subcommands, &["cargo", "install", "x"]).unwrap(),
+            "install x"
+        );
+        assert_eq!(
+            dispatch(&mut subcommands, &["cargo", "i", "x"]).unwrap(),
+            "install x"
+        );
+        assert_eq!(
+            dispatch(&mut subcommands, &["cargo", "make"]).unwrap(),
+            "build"
+        );
+
+        let err = dispatch(&mut subcommands, &["cargo", "-x", "bulid"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown subcommand \"bulid\"; valid subcommands: 'install' 'init' 'build'"
+        );
+        assert_eq!(
+            err.position(),
+            Some(Position {
+                index: 2,
+                offset: 0,
+                len: 5
+            })
+        );
+        // Abbreviations are off by default.
+        assert!(dispatch(&mut subcommands, &["cargo", "bu"]).is_err());
+        assert_eq!(
+            Subcommands::<()>::new()
+                .dispatch("x".into(), &mut Parser::from_args(&[] as &[&str]))
+                .unwrap_err()
+                .to_string(),
+            "unknown subcommand \"x\""
+        );
+    }
+
+    #[test]
+    fn abbreviations() {
+        let mut subcommands = subcommands();
+        subcommands.set_abbreviations(true);
+        assert_eq!(
+            dispatch(&mut subcommands, &["cargo", "bu"]).unwrap(),
+            "build"
+        );
+        assert_eq!(
+            dispatch(&mut subcommands, &["cargo", "ma"]).unwrap(),
+            "build"
+        );
+        assert_eq!(
+            dispatch(&mut subcommands, &["cargo", "ins", "x"]).unwrap(),
+            "install x"
+        );
+        // An exact match beats a longer one.
+        assert_eq!(
+            dispatch(&mut subcommands, &["cargo", "i", "x"]).unwrap(),
+            "install x"
+        );
+        assert_eq!(
+            dispatch(&mut subcommands, &["cargo", "in"])
+                .unwrap_err()
+                .to_string(),
+            "subcommand 'in' is ambiguous; possibilities: 'install' 'init'"
+        );
+        assert!(dispatch(&mut subcommands, &["cargo", ""]).is_err());
+    }
+
+    #[test]
+    fn nested() -> Result<(), Error> {
+        let mut parser = Parser::from_iter(&["git", "remote", "add", "--bad"]);
+        parser.set_keep_command_line(true);
+        let mut outer = Subcommands::new();
+        outer.add("remote", &[], |parser: &mut Parser| {
+            let mut inner = Subcommands::new();
+            inner.add("add", &[], |parser: &mut Parser| match parser.next()? {
+                Some(arg) => Err(arg.unexpected()),
+                None => Ok(()),
+            });
+            let name = parser.value()?;
+            inner.dispatch(name, parser)
+        });
+        let name = parser.value()?;
+        let err = outer.dispatch(name, &mut parser).unwrap_err();
+        assert_eq!(parser.subcommands(), &["remote", "add"]);
+        assert_eq!(parser.command_name().unwrap(), "git remote add");
+        assert_eq!(
+            parser.diagnostic(&err).to_string(),
+            "\
+git remote add: invalid option '--bad'
+  git remote add --bad
+                 ^^^^^"
+        );
+
+        let parser = Parser::from_args(&["x"]);
+        assert_eq!(parser.command_name(), None);
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic]
+    fn duplicate() {
+        let mut subcommands = subcommands();
+        subcommands.add("b", &[], |_: &mut Parser| Ok(String::new()));
+    }
+}
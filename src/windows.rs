@@ -0,0 +1,316 @@
+//! Splitting and quoting Windows command lines.
+//!
+//! On Windows a program receives its command line as a single string, and
+//! splits it into arguments itself. [`split`] does this the same way as
+//! [`CommandLineToArgvW`](https://learn.microsoft.com/en-us/windows/win32/api/shellapi/nf-shellapi-commandlinetoargvw)
+//! and the Microsoft C runtime (and therefore [`std::env::args_os`]), and
+//! [`join`] builds a command line that splits back into the same arguments.
+//!
+//! These functions are available on all platforms, so that the behavior of
+//! a Windows program can be tested elsewhere.
+//!
+//! # Example
+//! ```
+//! # fn main() -> Result<(), lexopt::Error> {
+//! use lexopt::prelude::*;
+//!
+//! let args = lexopt::windows::split(r#"C:\tools\app.exe /x "C:\My Files\\" -n"#);
+//! assert_eq!(args, &[r"C:\tools\app.exe", "/x", r"C:\My Files\", "-n"]);
+//!
+//! let mut parser = lexopt::Parser::from_iter(args);
+//! assert_eq!(parser.next()?, Some(Value("/x".into())));
+//! # Ok(()) }
+//! ```
+
+use std::ffi::{OsStr, OsString};
+
+#[cfg(windows)]
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+
+const SPACE: u16 = b' ' as u16;
+const TAB: u16 = b'\t' as u16;
+const QUOTE: u16 = b'"' as u16;
+const BACKSLASH: u16 = b'\\' as u16;
+
+/// Split a Windows command line into arguments.
+///
+/// The first argument is the program name. It ends at the first space or
+/// tab outside of quotes, and backslashes have no special meaning in it.
+///
+/// The other arguments are separated by spaces and tabs outside of quotes,
+/// and:
+/// - `2n` backslashes followed by a quote produce `n` backslashes, and the
+///   quote starts or ends a quoted section.
+/// - `2n + 1` backslashes followed by a quote produce `n` backslashes and a
+///   literal quote.
+/// - Other backslashes are taken literally.
+/// - Inside a quoted section, two quotes in a row produce a literal quote.
+///
+/// An empty command line produces no arguments. (`CommandLineToArgvW` would
+/// return the path of the current executable instead.)
+///
+/// On platforms other than Windows, invalid unicode is replaced by `�`.
+pub fn split<S: AsRef<OsStr> + ?Sized>(command_line: &S) -> Vec<OsString> {
+    let command_line = command_line.as_ref();
+    #[cfg(windows)]
+    {
+        let units: Vec<u16> = command_line.encode_wide().collect();
+        split_units(&units)
+            .iter()
+            .map(|arg| OsString::from_wide(arg))
+            .collect()
+    }
+    #[cfg(not(windows))]
+    {
+        let units: Vec<u16> = command_line.to_string_lossy().encode_utf16().collect();
+        split_units(&units)
+            .iter()
+            .map(|arg| String::from_utf16_lossy(arg).into())
+            .collect()
+    }
+}
+
+fn split_units(units: &[u16]) -> Vec<Vec<u16>> {
+    let mut args = Vec::new();
+    if units.is_empty() {
+        return args;
+    }
+
+    // The program name. Quotes toggle quoting, and there are no escapes.
+    let mut units = units.iter().cloned().peekable();
+    let mut in_quotes = false;
+    let mut arg = Vec::new();
+    for unit in &mut units {
+        match unit {
+            QUOTE => in_quotes = !in_quotes,
+            SPACE | TAB if !in_quotes => break,
+            _ => arg.push(unit),
+        }
+    }
+    args.push(arg);
+
+    let mut arg = Vec::new();
+    in_quotes = false;
+    // Whether the current argument exists even if it's empty.
+    let mut started = false;
+    let mut backslashes = 0;
+    while let Some(unit) = units.next() {
+        if unit == BACKSLASH {
+            backslashes += 1;
+            continue;
+        }
+        if unit == QUOTE {
+            arg.extend(std::iter::repeat(BACKSLASH).take(backslashes / 2));
+            started = true;
+            if backslashes % 2 == 1 {
+                arg.push(QUOTE);
+            } else if in_quotes && units.peek() == Some(&QUOTE) {
+                arg.push(QUOTE);
+                units.next();
+            } else {
+                in_quotes = !in_quotes;
+            }
+            backslashes = 0;
+            continue;
+        }
+        arg.extend(std::iter::repeat(BACKSLASH).take(backslashes));
+        backslashes = 0;
+        match unit {
+            SPACE | TAB if !in_quotes => {
+                if started || !arg.is_empty() {
+                    args.push(std::mem::replace(&mut arg, Vec::new()));
+                }
+                started = false;
+            }
+            _ => arg.push(unit),
+        }
+    }
+    arg.extend(std::iter::repeat(BACKSLASH).take(backslashes));
+    if started || !arg.is_empty() {
+        args.push(arg);
+    }
+    args
+}
+
+/// Quote an argument so that [`split`] returns it unchanged.
+///
+/// The argument is put in quotes if it's empty or contains a space or tab.
+/// Quotes are escaped with backslashes, and so are backslashes that come
+/// right before a quote.
+///
+/// This is not suitable for the program name, see [`join`].
+///
+/// # Example
+/// ```
+/// use lexopt::windows::quote;
+///
+/// assert_eq!(quote(r"C:\tools"), r"C:\tools");
+/// assert_eq!(quote(r"C:\My Files\"), r#""C:\My Files\\""#);
+/// assert_eq!(quote(r#"say "hi""#), r#""say \"hi\"""#);
+/// ```
+pub fn quote<S: AsRef<OsStr> + ?Sized>(arg: &S) -> OsString {
+    from_units(quote_units(&to_units(arg.as_ref())))
+}
+
+/// Build a Windows command line from a program name and arguments.
+///
+/// The program name is quoted if it's empty or contains a space or tab,
+/// and the other arguments are quoted by [`quote`].
+///
+/// # Panics
+///
+/// Panics if the program name contains a quote (`"`), because there's no
+/// way to represent it.
+///
+/// # Example
+/// ```
+/// let line = lexopt::windows::join(&[r"C:\Program Files\app.exe", "a b", "c"]);
+/// assert_eq!(line, r#""C:\Program Files\app.exe" "a b" c"#);
+/// ```
+pub fn join<I>(args: I) -> OsString
+where
+    I: IntoIterator,
+    I::Item: AsRef<OsStr>,
+{
+    let mut line = Vec::new();
+    for (i, arg) in args.into_iter().enumerate() {
+        let arg = to_units(arg.as_ref());
+        if i == 0 {
+            assert!(
+                !arg.contains(&QUOTE),
+                "program name {:?} contains a quote",
+                String::from_utf16_lossy(&arg)
+            );
+            if arg.is_empty() || arg.contains(&SPACE) || arg.contains(&TAB) {
+                line.push(QUOTE);
+                line.extend(arg);
+                line.push(QUOTE);
+            } else {
+                line.extend(arg);
+            }
+        } else {
+            line.push(SPACE);
+            line.extend(quote_units(&arg));
+        }
+    }
+    from_units(line)
+}
+
+fn quote_units(arg: &[u16]) -> Vec<u16> {
+    let quoted = arg.is_empty() || arg.contains(&SPACE) || arg.contains(&TAB);
+    let mut result = Vec::with_capacity(arg.len() + 2);
+    if quoted {
+        result.push(QUOTE);
+    }
+    let mut backslashes = 0;
+    for &unit in arg {
+        if unit == BACKSLASH {
+            backslashes += 1;
+        } else {
+            if unit == QUOTE {
+                // 2n + 1 backslashes produce n backslashes and a quote.
+                result.extend(std::iter::repeat(BACKSLASH).take(backslashes + 1));
+            }
+            backslashes = 0;
+        }
+        result.push(unit);
+    }
+    if quoted {
+        // 2n backslashes before the closing quote produce n backslashes.
+        result.extend(std::iter::repeat(BACKSLASH).take(backslashes));
+        result.push(QUOTE);
+    }
+    result
+}
+
+fn to_units(text: &OsStr) -> Vec<u16> {
+    #[cfg(windows)]
+    {
+        text.encode_wide().collect()
+    }
+    #[cfg(not(windows))]
+    {
+        text.to_string_lossy().encode_utf16().collect()
+    }
+}
+
+fn from_units(units: Vec<u16>) -> OsString {
+    #[cfg(windows)]
+    {
+        OsString::from_wide(&units)
+    }
+    #[cfg(not(windows))]
+    {
+        String::from_utf16_lossy(&units).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(line: &str, expected: &[&str]) {
+        assert_eq!(split(line), expected, "{}", line);
+    }
+
+    #[test]
+    fn splitting() {
+        check("", &[]);
+        check("EXE", &["EXE"]);
+        check(" EXE", &["", "EXE"]);
+        check("EXE  ", &["EXE"]);
+        check(
+            r#""C:\Program Files\app.exe" a"#,
+            &[r"C:\Program Files\app.exe", "a"],
+        );
+        check(r#"C:\"My Dir"\app.exe a"#, &[r"C:\My Dir\app.exe", "a"]);
+        check(r#"a\"b c"#, &[r"a\b c"]);
+        check(r#"a\"b" c"#, &[r"a\b", "c"]);
+        check("EXE a\tb  c ", &["EXE", "a", "b", "c"]);
+
+        // From Microsoft's documentation
+        check(r#"EXE "abc" d e"#, &["EXE", "abc", "d", "e"]);
+        check(r#"EXE a\\b d"e f"g h"#, &["EXE", r"a\\b", "de fg", "h"]);
+        check(r#"EXE a\\\"b c d"#, &["EXE", r#"a\"b"#, "c", "d"]);
+        check(r#"EXE a\\\\"b c" d e"#, &["EXE", r"a\\b c", "d", "e"]);
+        check(r#"EXE a"b"" c d"#, &["EXE", r#"ab" c d"#]);
+
+        check(r#"EXE "" """" "\""#, &["EXE", "", r#"""#, r#"""#]);
+        check(r#"EXE a "" b"#, &["EXE", "a", "", "b"]);
+        check(r#"EXE ""a"" "unterminated"#, &["EXE", "a", "unterminated"]);
+        check(r"EXE trailing\\", &["EXE", r"trailing\\"]);
+        check(r#"EXE "a\\" b"#, &["EXE", r"a\", "b"]);
+        check("EXE ünïcödé", &["EXE", "ünïcödé"]);
+    }
+
+    #[test]
+    fn quoting() {
+        let args = [
+            r"C:\Program Files\app.exe",
+            "",
+            "plain",
+            "two words",
+            r#"""#,
+            r"C:\dir\",
+            r"C:\my dir\",
+            r#"\\"quoted\\""#,
+            "tab\there",
+            "ü",
+        ];
+        let line = join(&args);
+        assert_eq!(
+            line,
+            r#""C:\Program Files\app.exe" "" plain "two words" \" C:\dir\ "C:\my dir\\" \\\\\"quoted\\\\\" "tab	here" ü"#
+        );
+        assert_eq!(split(&line), args);
+        assert_eq!(join(&[""]), r#""""#);
+        assert_eq!(split(r#""""#), &[""]);
+        assert_eq!(join(&[] as &[&str]), "");
+    }
+
+    #[test]
+    #[should_panic]
+    fn quote_in_program_name() {
+        join(&[r#"a"b"#]);
+    }
+}